@@ -0,0 +1,142 @@
+use crate::conflict::{ConflictGraph, SectionIds};
+use crate::schedule::{get_potential_schedules, is_conflict};
+use crate::structs::*;
+use std::time::{Duration, Instant};
+
+///Times schedule generation and alternate finding with and without the conflict graph
+pub fn run_bench(inputs: &ScheduleInputs, constraints: &Constraints, runs: usize) {
+    let runs: usize = runs.max(1);
+    let mut direct_times: Vec<Duration> = Vec::new();
+    let mut graph_times: Vec<Duration> = Vec::new();
+    let mut direct_counts: (usize, usize) = (0, 0);
+    let mut graph_counts: (usize, usize) = (0, 0);
+
+    for _ in 0..runs {
+        let start: Instant = Instant::now();
+        direct_counts = direct_path(inputs, constraints);
+        direct_times.push(start.elapsed());
+
+        let start: Instant = Instant::now();
+        graph_counts = graph_path(inputs, constraints);
+        graph_times.push(start.elapsed());
+    }
+
+    println!("{} run(s), schedules / alternates found:", runs);
+    println!(
+        "  direct is_conflict: {:>10?} best, {:>10?} mean  ({} / {})",
+        best(&direct_times),
+        mean(&direct_times),
+        direct_counts.0,
+        direct_counts.1
+    );
+    println!(
+        "  conflict graph:     {:>10?} best, {:>10?} mean  ({} / {})",
+        best(&graph_times),
+        mean(&graph_times),
+        graph_counts.0,
+        graph_counts.1
+    );
+    if direct_counts != graph_counts {
+        println!("  WARNING: the two paths disagree");
+    }
+}
+
+fn best(times: &[Duration]) -> Duration {
+    times.iter().min().copied().unwrap_or_default()
+}
+
+fn mean(times: &[Duration]) -> Duration {
+    times.iter().sum::<Duration>() / times.len().max(1) as u32
+}
+
+///Builds the graph, then generates schedules and alternates from it
+fn graph_path(inputs: &ScheduleInputs, constraints: &Constraints) -> (usize, usize) {
+    let graph: ConflictGraph = ConflictGraph::new(inputs, constraints);
+    let schedules: Vec<SectionIds> = get_potential_schedules(&graph);
+    let alternates: usize = schedules
+        .iter()
+        .map(|schedule| {
+            schedule
+                .iter()
+                .map(|&id| graph.find_alt(schedule, id).len())
+                .sum::<usize>()
+        })
+        .sum();
+    (schedules.len(), alternates)
+}
+
+///Same search, but calling is_conflict every time two sections are compared
+fn direct_path(inputs: &ScheduleInputs, constraints: &Constraints) -> (usize, usize) {
+    let mut levels: Vec<Vec<&Section>> = usable(&inputs.desired_courses, inputs, constraints);
+    levels.sort_by_key(|sections| sections.len());
+    let alternates: Vec<&Section> = usable(&inputs.alternate_courses, inputs, constraints)
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let mut schedules: Vec<Vec<&Section>> = Vec::new();
+    let mut chosen: Vec<&Section> = Vec::new();
+    search(&levels, &mut chosen, &mut schedules, inputs, constraints);
+
+    let mut alternate_count: usize = 0;
+    for schedule in &schedules {
+        for replacing in schedule {
+            alternate_count += alternates
+                .iter()
+                .filter(|alt| {
+                    !schedule.iter().filter(|s| s != &replacing).any(|s| {
+                        is_conflict(s, alt, inputs.walk.as_ref(), constraints)
+                    })
+                })
+                .count();
+        }
+    }
+    (schedules.len(), alternate_count)
+}
+
+///Usable sections of each course, courses and sections in code order
+fn usable<'a>(
+    courses: &'a CourseMap,
+    inputs: &ScheduleInputs,
+    constraints: &Constraints,
+) -> Vec<Vec<&'a Section>> {
+    let mut names: Vec<&String> = courses.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let mut sections: Vec<&Section> = courses[name]
+                .values()
+                .filter(|s| s.is_allowed(inputs.walk.as_ref(), constraints))
+                .collect();
+            sections.sort_by(|a, b| a.section.cmp(&b.section));
+            sections
+        })
+        .collect()
+}
+
+fn search<'a>(
+    levels: &[Vec<&'a Section>],
+    chosen: &mut Vec<&'a Section>,
+    schedules: &mut Vec<Vec<&'a Section>>,
+    inputs: &ScheduleInputs,
+    constraints: &Constraints,
+) {
+    let Some(level) = levels.get(chosen.len()) else {
+        if !levels.is_empty() {
+            schedules.push(chosen.clone());
+        }
+        return;
+    };
+    for candidate in level {
+        if chosen
+            .iter()
+            .any(|s| is_conflict(s, candidate, inputs.walk.as_ref(), constraints))
+        {
+            continue;
+        }
+        chosen.push(candidate);
+        search(levels, chosen, schedules, inputs, constraints);
+        chosen.pop();
+    }
+}
@@ -0,0 +1,135 @@
+//!Stand-in for api.umd.io and planetterp.com that serves recorded json responses, so the
+//!whole fetch path can run without network access. Point the scheduler at it with
+//!`--umd-url http://127.0.0.1:7879 --planetterp-url http://127.0.0.1:7879`
+use axum::{
+    Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use clap::Parser;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "fixture_server", about = "Serves recorded umd.io and planetterp responses")]
+struct Args {
+    ///Directory of recordings, laid out as umd/SEMESTER/COURSE.json and planetterp/FIRST LAST.json
+    #[arg(default_value = "fixtures")]
+    dir: PathBuf,
+
+    ///Address to listen on
+    #[arg(long, default_value = "127.0.0.1:7879")]
+    addr: String,
+
+    ///Fail the first N requests for every url, with a 429 (Retry-After: 1) and then 503s
+    #[arg(long, default_value_t = 0)]
+    flaky: u32,
+}
+
+struct Fixtures {
+    dir: PathBuf,
+    flaky: u32,
+    //requests seen so far for every url
+    seen: Mutex<HashMap<String, u32>>,
+}
+
+impl Fixtures {
+    ///The failure to answer with when the url hasn't been failed often enough yet
+    fn failure(&self, url: &str) -> Option<Response> {
+        let mut seen = self.seen.lock().unwrap();
+        let count: &mut u32 = seen.entry(url.to_string()).or_default();
+        *count += 1;
+        match *count {
+            n if n > self.flaky => None,
+            1 => Some((StatusCode::TOO_MANY_REQUESTS, [("retry-after", "1")]).into_response()),
+            _ => Some(StatusCode::SERVICE_UNAVAILABLE.into_response()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SemesterQuery {
+    semester: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NameQuery {
+    name: String,
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Args = Args::parse();
+    let fixtures: Arc<Fixtures> = Arc::new(Fixtures {
+        dir: args.dir,
+        flaky: args.flaky,
+        seen: Mutex::new(HashMap::new()),
+    });
+
+    let app = Router::new()
+        .route("/v1/courses/{course}/sections", get(sections))
+        .route("/api/v1/professor", get(professor))
+        .with_state(fixtures);
+
+    let listener: tokio::net::TcpListener = tokio::net::TcpListener::bind(&args.addr)
+        .await
+        .unwrap();
+    eprintln!("Serving fixtures on http://{}", args.addr);
+    axum::serve(listener, app.into_make_service())
+        .await
+        .unwrap();
+}
+
+///Same path and query as umd.io's course sections endpoint
+async fn sections(
+    State(fixtures): State<Arc<Fixtures>>,
+    Path(course): Path<String>,
+    Query(query): Query<SemesterQuery>,
+) -> Response {
+    if let Some(failure) = fixtures.failure(&format!("{}/{}", query.semester, course)) {
+        return failure;
+    }
+    let path: PathBuf = fixtures
+        .dir
+        .join("umd")
+        .join(&query.semester)
+        .join(format!("{}.json", course));
+    recorded(&path, "course not found")
+}
+
+///Same path and query as planetterp's professor endpoint
+async fn professor(
+    State(fixtures): State<Arc<Fixtures>>,
+    Query(query): Query<NameQuery>,
+) -> Response {
+    if let Some(failure) = fixtures.failure(&query.name) {
+        return failure;
+    }
+    let path: PathBuf = fixtures
+        .dir
+        .join("planetterp")
+        .join(format!("{}.json", query.name));
+    recorded(&path, "professor not found")
+}
+
+///The recorded response at path, or a 404 with a json error like the real apis give
+fn recorded(path: &std::path::Path, missing: &str) -> Response {
+    match std::fs::read_to_string(path) {
+        Ok(body) => ([("content-type", "application/json")], body).into_response(),
+        Err(_) => {
+            eprintln!("No recording at {}", path.display());
+            (
+                StatusCode::NOT_FOUND,
+                [("content-type", "application/json")],
+                format!(r#"{{"error_code":404,"message":"{}"}}"#, missing),
+            )
+                .into_response()
+        }
+    }
+}
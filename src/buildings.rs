@@ -0,0 +1,163 @@
+use crate::structs::{BuildingMap, CourseMap, SectionMap};
+use crate::walk::WalkModel;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+//about the width of campus, so no walk between two real buildings is longer
+pub const WORST_CASE_WALK: f32 = 2000.0;
+
+///What to assume about a meeting in a building missing from the building file,
+///and about a walk the walk model can't time
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownBuildings {
+    ///Walking to or from it takes as long as crossing campus
+    #[default]
+    WorstCase,
+    ///It takes no time to walk to or from it
+    NoWalk,
+    ///Leave out sections that meet in it, and never put classes the walk model can't time
+    ///the walk between on the same day
+    Reject,
+}
+
+impl UnknownBuildings {
+    ///Meters walked to or from an unknown building. Rejected sections never get this far,
+    ///and rejected walks are conflicts before this is asked
+    pub fn walk_distance(self) -> f32 {
+        match self {
+            UnknownBuildings::WorstCase | UnknownBuildings::Reject => WORST_CASE_WALK,
+            UnknownBuildings::NoWalk => 0.0,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            UnknownBuildings::WorstCase => "assuming the longest walk on campus",
+            UnknownBuildings::NoWalk => "assuming no walk",
+            UnknownBuildings::Reject => "leaving out their sections",
+        }
+    }
+}
+
+///Every building code the courses meet in that isn't in the building file, with the sections meeting there
+pub fn unknown_buildings<'a>(
+    courses: impl Iterator<Item = &'a SectionMap>,
+    buildings: &BuildingMap,
+) -> BTreeMap<String, Vec<String>> {
+    let mut unknown: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for section in courses.flat_map(|sections| sections.values()) {
+        let name: String = format!("{}-{}", section.course, section.section);
+        for meeting in section.classtimes.values().flatten() {
+            //meetings without a building (online, blockouts without a location) have nowhere to walk to
+            if meeting.building.is_empty() || buildings.contains_key(&meeting.building) {
+                continue;
+            }
+            let sections: &mut Vec<String> = unknown.entry(meeting.building.clone()).or_default();
+            if !sections.contains(&name) {
+                sections.push(name.clone());
+            }
+        }
+    }
+    unknown
+}
+
+///Lines reporting the unknown buildings and what was done about them
+pub fn unknown_report(
+    unknown: &BTreeMap<String, Vec<String>>,
+    policy: UnknownBuildings,
+) -> Vec<String> {
+    unknown
+        .iter()
+        .map(|(code, sections)| {
+            format!(
+                "Unknown building {} ({}), {}. Add it with `scheduler add-building {} LAT LONG`",
+                code,
+                sections.join(", "),
+                policy.describe(),
+                code
+            )
+        })
+        .collect()
+}
+
+///Every pair of the given buildings the walk model has no time for, each pair once in code order
+pub fn untimed_walks<'a>(
+    codes: impl Iterator<Item = &'a str>,
+    walk: &dyn WalkModel,
+    walk_speed: f32,
+) -> Vec<(String, String)> {
+    let codes: BTreeSet<&str> = codes.filter(|code| !code.is_empty()).collect();
+    let mut untimed: Vec<(String, String)> = Vec::new();
+    for (i, &from) in codes.iter().enumerate() {
+        for &to in codes.iter().skip(i + 1) {
+            if walk.seconds(from, to, walk_speed).is_none()
+                || walk.seconds(to, from, walk_speed).is_none()
+            {
+                untimed.push((from.to_string(), to.to_string()));
+            }
+        }
+    }
+    untimed
+}
+
+///Lines reporting the walks the walk model can't time and what was done about them
+pub fn untimed_report(untimed: &[(String, String)], policy: UnknownBuildings) -> Vec<String> {
+    let handling: &str = match policy {
+        UnknownBuildings::Reject => "never putting classes in both on the same day",
+        _ => policy.describe(),
+    };
+    untimed
+        .iter()
+        .map(|(from, to)| {
+            format!(
+                "The walk model has no walking time between {} and {}, {}",
+                from, to, handling
+            )
+        })
+        .collect()
+}
+
+///Removes every section meeting in one of the unknown buildings
+pub fn reject_sections(courses: &mut CourseMap, unknown: &BTreeMap<String, Vec<String>>) {
+    for sections in courses.values_mut() {
+        sections.retain(|_, section| {
+            !section
+                .classtimes
+                .values()
+                .flatten()
+                .any(|meeting| unknown.contains_key(&meeting.building))
+        });
+    }
+}
+
+///Reads the building file
+pub fn read_buildings(path: &Path) -> Result<BuildingMap, Box<dyn std::error::Error>> {
+    let raw: String = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&raw)?)
+}
+
+///Adds a building to the building file, or moves it if it is already there.
+///Anything else stored for the other buildings, and their order, is kept as it is.
+///Gives back whether it was new
+pub fn add_building(
+    path: &Path,
+    code: &str,
+    lat: f64,
+    long: f64,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut buildings: Map<String, Value> = match fs::read_to_string(path) {
+        Ok(raw) => serde_json::from_str(&raw)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Map::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let added: bool = buildings
+        .insert(code.to_string(), json!({"long": long, "lat": lat}))
+        .is_none();
+    fs::write(path, serde_json::to_string_pretty(&buildings)?)?;
+    Ok(added)
+}
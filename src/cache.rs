@@ -0,0 +1,174 @@
+use crate::structs::SectionMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+///How cached data is used for a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    ///Use cached data while it is fresh, fetch (and cache) everything else
+    Normal,
+    ///Only use cached data, however old, and never go to the network
+    Offline,
+    ///Fetch everything again and overwrite the cache
+    Refresh,
+}
+
+///Sections of one course in one semester, as saved on disk
+#[derive(Debug, Deserialize, Serialize)]
+struct CachedCourse {
+    //seconds since the unix epoch
+    fetched_at: u64,
+    sections: SectionMap,
+}
+
+///Fetched course sections saved under `dir`, one file per course and semester
+#[derive(Debug, Clone)]
+pub struct CourseCache {
+    pub dir: PathBuf,
+    //how long fetched sections are used before they are fetched again
+    pub ttl: Duration,
+    pub mode: CacheMode,
+}
+
+impl CourseCache {
+    pub fn new(dir: PathBuf, ttl: Duration, mode: CacheMode) -> CourseCache {
+        CourseCache { dir, ttl, mode }
+    }
+
+    fn path(&self, course: &str, semester: &str) -> PathBuf {
+        self.dir
+            .join("courses")
+            .join(semester)
+            .join(format!("{}.json", course))
+    }
+
+    ///Cached sections of the course that this run is allowed to use, with how old they are
+    pub fn load(&self, course: &str, semester: &str) -> Option<(SectionMap, Duration)> {
+        if self.mode == CacheMode::Refresh {
+            return None;
+        }
+        let cached: CachedCourse = read_json(&self.path(course, semester))?;
+        let age: Duration = now().saturating_sub(Duration::from_secs(cached.fetched_at));
+        if self.mode == CacheMode::Normal && age > self.ttl {
+            return None;
+        }
+        Some((cached.sections, age))
+    }
+
+    ///Saves freshly fetched sections. Failing to write only costs a fetch next time, so it is just reported
+    pub fn store(&self, course: &str, semester: &str, sections: &SectionMap) {
+        let cached: CachedCourse = CachedCourse {
+            fetched_at: now().as_secs(),
+            sections: sections.clone(),
+        };
+        if let Err(e) = write_json(&self.path(course, semester), &cached) {
+            eprintln!("Could not cache course {}: {}", course, e);
+        }
+    }
+}
+
+///A professor's rating, as saved on disk
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+struct CachedRating {
+    //None when the provider doesn't know the professor (TAs, "Staff"), so they aren't looked up again
+    rating: Option<f32>,
+    //seconds since the unix epoch
+    fetched_at: u64,
+}
+
+///Professor ratings shared by every course fetched in a run and saved to a single file
+#[derive(Debug)]
+pub struct RatingCache {
+    path: PathBuf,
+    ttl: Duration,
+    pub mode: CacheMode,
+    //everything read from or headed to disk, by professor name
+    stored: Mutex<BTreeMap<String, CachedRating>>,
+    //whether anything new was added this run
+    changed: AtomicBool,
+}
+
+impl RatingCache {
+    ///Reads the saved ratings at `path`, starting empty if there are none
+    pub fn load(path: PathBuf, ttl: Duration, mode: CacheMode) -> RatingCache {
+        let stored: BTreeMap<String, CachedRating> = match mode {
+            CacheMode::Refresh => BTreeMap::new(),
+            _ => read_json(&path).unwrap_or_default(),
+        };
+        RatingCache {
+            path,
+            ttl,
+            mode,
+            stored: Mutex::new(stored),
+            changed: AtomicBool::new(false),
+        }
+    }
+
+    ///The professor's cached rating, if it is fresh enough for this run (any age offline).
+    ///Some(None) means the provider was asked and didn't know them
+    pub fn get(&self, professor: &str) -> Option<Option<f32>> {
+        let stored = self.stored.lock().unwrap();
+        let cached: &CachedRating = stored.get(professor)?;
+        let age: Duration = now().saturating_sub(Duration::from_secs(cached.fetched_at));
+        if self.mode == CacheMode::Normal && age > self.ttl {
+            return None;
+        }
+        Some(cached.rating)
+    }
+
+    ///Keeps a freshly looked up rating, or that there is none. Only lookups that got an answer
+    ///are kept, so failed ones are tried again next run
+    pub fn insert(&self, professor: &str, rating: Option<f32>) {
+        self.stored.lock().unwrap().insert(
+            professor.to_string(),
+            CachedRating {
+                rating,
+                fetched_at: now().as_secs(),
+            },
+        );
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    ///Writes the ratings back to disk when anything new was looked up
+    pub fn save(&self) {
+        if !self.changed.load(Ordering::Relaxed) {
+            return;
+        }
+        let stored = self.stored.lock().unwrap();
+        if let Err(e) = write_json(&self.path, &*stored) {
+            eprintln!("Could not cache professor ratings: {}", e);
+        }
+    }
+}
+
+///Time since the unix epoch
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+///Reads a json file, treating a missing or unreadable file as nothing cached
+pub fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<T> {
+    let raw: String = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&raw) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("Ignoring unreadable cache file {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(value)?)?;
+    Ok(())
+}
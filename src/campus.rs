@@ -0,0 +1,426 @@
+use crate::cache::{read_json, write_json};
+use crate::structs::{BuildingData, BuildingMap};
+use crate::walk::WalkModel;
+use haversine_rs::{distance, point::Point, units::Unit};
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+///Meters walked between buildings, keyed by building and then by the building walked to
+pub type WalkMeters = BTreeMap<String, BTreeMap<String, f32>>;
+
+///The campus's walkways: points joined by paths of known length
+#[derive(Debug, Default)]
+pub struct CampusGraph {
+    //coordinates of every point, by index
+    nodes: Vec<(f64, f64)>,
+    //paths leaving every point: (the point it leads to, meters)
+    edges: Vec<Vec<(usize, f64)>>,
+    //buildings whose entrance is given with the graph: the point it joins the paths at, and the
+    //straight walk from the entrance to there
+    entrances: HashMap<String, (usize, f64)>,
+}
+
+///A path graph written as plain json
+#[derive(Debug, Deserialize)]
+struct GraphFile {
+    //points by id. A point with a building's code as its id is that building's entrance
+    nodes: HashMap<String, Coordinates>,
+    //[from, to] or [from, to, meters]. Paths without a length are measured straight
+    edges: Vec<EdgeFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Coordinates {
+    lat: f64,
+    long: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum EdgeFile {
+    Measured(String, String, f64),
+    Straight(String, String),
+}
+
+///The parts of a GeoJSON FeatureCollection a path graph uses
+#[derive(Debug, Deserialize)]
+struct GeoJson {
+    features: Vec<Feature>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Feature {
+    geometry: Geometry,
+    #[serde(default)]
+    properties: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum Geometry {
+    //[long, lat]
+    Point {
+        coordinates: [f64; 2],
+    },
+    LineString {
+        coordinates: Vec<[f64; 2]>,
+    },
+    //polygons and the like aren't paths
+    #[serde(other)]
+    Other,
+}
+
+fn meters_between(a: (f64, f64), b: (f64, f64)) -> f64 {
+    distance(Point::new(a.0, a.1), Point::new(b.0, b.1), Unit::Meters)
+}
+
+impl CampusGraph {
+    ///Loads a path graph from json ({"nodes": {...}, "edges": [...]}) or GeoJSON, where every
+    ///LineString is a path and Points with a "building" property are building entrances.
+    ///`raw` was read from `path`, whose extension picks the format
+    fn parse(raw: &str, path: &Path) -> Result<CampusGraph, Box<dyn std::error::Error>> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => CampusGraph::from_json(serde_json::from_str(raw)?),
+            Some("geojson") => Ok(CampusGraph::from_geojson(serde_json::from_str(raw)?)),
+            _ => Err(format!("{} is not a .json or .geojson file", path.display()).into()),
+        }
+    }
+
+    fn from_json(file: GraphFile) -> Result<CampusGraph, Box<dyn std::error::Error>> {
+        let mut graph: CampusGraph = CampusGraph::default();
+        //sorted so points get the same index every time
+        let ids: BTreeMap<&String, &Coordinates> = file.nodes.iter().collect();
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for (id, coordinates) in ids {
+            let node: usize = graph.add_node((coordinates.lat, coordinates.long));
+            index.insert(id.as_str(), node);
+            graph.entrances.insert(id.clone(), (node, 0.0));
+        }
+        for edge in &file.edges {
+            let (from, to, meters) = match edge {
+                EdgeFile::Measured(from, to, meters) => (from, to, Some(*meters)),
+                EdgeFile::Straight(from, to) => (from, to, None),
+            };
+            let node = |id: &String| {
+                index
+                    .get(id.as_str())
+                    .copied()
+                    .ok_or_else(|| format!("path {}-{} uses unknown point {}", from, to, id))
+            };
+            //a negative or NaN length would let Dijkstra settle on walks that aren't the shortest
+            if let Some(meters) = meters
+                && !(meters >= 0.0 && meters.is_finite())
+            {
+                return Err(format!(
+                    "path {}-{} is {} meters long, it needs a length of 0 or more",
+                    from, to, meters
+                )
+                .into());
+            }
+            graph.add_edge(node(from)?, node(to)?, meters);
+        }
+        Ok(graph)
+    }
+
+    fn from_geojson(file: GeoJson) -> CampusGraph {
+        let mut graph: CampusGraph = CampusGraph::default();
+        //lines meet where they share a coordinate
+        let mut index: HashMap<(i64, i64), usize> = HashMap::new();
+        let mut node = |graph: &mut CampusGraph, [long, lat]: [f64; 2]| -> usize {
+            let key: (i64, i64) = ((lat * 1e7).round() as i64, (long * 1e7).round() as i64);
+            *index
+                .entry(key)
+                .or_insert_with(|| graph.add_node((lat, long)))
+        };
+        let mut entrances: Vec<(String, [f64; 2])> = Vec::new();
+        for feature in file.features {
+            match feature.geometry {
+                Geometry::LineString { coordinates } => {
+                    for pair in coordinates.windows(2) {
+                        let (from, to) = (node(&mut graph, pair[0]), node(&mut graph, pair[1]));
+                        graph.add_edge(from, to, None);
+                    }
+                }
+                Geometry::Point { coordinates } => {
+                    if let Some(code) = feature.properties.get("building").and_then(|b| b.as_str())
+                    {
+                        entrances.push((code.to_string(), coordinates));
+                    }
+                }
+                Geometry::Other => {}
+            }
+        }
+        //entrances rarely sit exactly on a line, so they join the paths at the closest point
+        //once every line is in
+        for (code, [long, lat]) in entrances {
+            if let Some(entrance) = graph.nearest((lat, long)) {
+                graph.entrances.insert(code, entrance);
+            }
+        }
+        graph
+    }
+
+    fn add_node(&mut self, coordinates: (f64, f64)) -> usize {
+        self.nodes.push(coordinates);
+        self.edges.push(Vec::new());
+        self.nodes.len() - 1
+    }
+
+    ///Paths can be walked both ways
+    fn add_edge(&mut self, from: usize, to: usize, meters: Option<f64>) {
+        let meters: f64 =
+            meters.unwrap_or_else(|| meters_between(self.nodes[from], self.nodes[to]));
+        self.edges[from].push((to, meters));
+        self.edges[to].push((from, meters));
+    }
+
+    ///The point closest to `at`, and the straight walk from `at` to there
+    fn nearest(&self, at: (f64, f64)) -> Option<(usize, f64)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(node, &coordinates)| (node, meters_between(at, coordinates)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    ///Where a building joins the graph, and the straight walk from the building to there.
+    ///Buildings without an entrance in the graph join it at the nearest point
+    fn entrance(&self, code: &str, building: &BuildingData) -> Option<(usize, f64)> {
+        if let Some(&entrance) = self.entrances.get(code) {
+            return Some(entrance);
+        }
+        self.nearest((building.lat as f64, building.long as f64))
+    }
+
+    ///Shortest walk in meters from `start` to every point, infinite for points that can't be reached
+    fn dijkstra(&self, start: usize) -> Vec<f64> {
+        let mut best: Vec<f64> = vec![f64::INFINITY; self.nodes.len()];
+        let mut queue: BinaryHeap<Visit> = BinaryHeap::new();
+        best[start] = 0.0;
+        queue.push(Visit {
+            meters: 0.0,
+            node: start,
+        });
+        while let Some(Visit { meters, node }) = queue.pop() {
+            //a shorter way here was already found
+            if meters > best[node] {
+                continue;
+            }
+            for &(next, length) in &self.edges[node] {
+                let through: f64 = meters + length;
+                if through < best[next] {
+                    best[next] = through;
+                    queue.push(Visit {
+                        meters: through,
+                        node: next,
+                    });
+                }
+            }
+        }
+        best
+    }
+
+    ///Shortest walks between every pair of buildings, one Dijkstra search per building.
+    ///Pairs the paths don't connect are left out
+    pub fn all_pairs(&self, buildings: &BuildingMap) -> WalkMeters {
+        let entrances: BTreeMap<&String, (usize, f64)> = buildings
+            .iter()
+            .filter_map(|(code, building)| Some((code, self.entrance(code, building)?)))
+            .collect();
+
+        let mut walks: WalkMeters = WalkMeters::new();
+        for (from, &(start, walk_in)) in &entrances {
+            let best: Vec<f64> = self.dijkstra(start);
+            let reachable = entrances.iter().filter_map(|(to, &(end, walk_out))| {
+                //staying in a building isn't a walk out to the paths and back
+                let meters: f64 = match from == to {
+                    true => 0.0,
+                    false => walk_in + best[end] + walk_out,
+                };
+                meters.is_finite().then(|| ((*to).clone(), meters as f32))
+            });
+            walks.insert((*from).clone(), reachable.collect());
+        }
+        walks
+    }
+}
+
+///A point waiting to be visited, closest first
+#[derive(Debug, PartialEq)]
+struct Visit {
+    meters: f64,
+    node: usize,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        //reversed, BinaryHeap pops the largest
+        other
+            .meters
+            .total_cmp(&self.meters)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+///Walks along the campus's paths, shortest way between buildings
+pub struct PathWalks {
+    meters: WalkMeters,
+}
+
+impl PathWalks {
+    ///Shortest walks for the graph in `path` and these buildings. They are worked out once and
+    ///kept in `cache_dir`, under a name that changes whenever the graph or the buildings do
+    pub fn load(
+        path: &Path,
+        buildings: &BuildingMap,
+        cache_dir: &Path,
+    ) -> Result<PathWalks, Box<dyn std::error::Error>> {
+        let raw: String = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        let cached: PathBuf = cache_file(&raw, buildings, cache_dir);
+
+        if let Some(meters) = read_json(&cached) {
+            return Ok(PathWalks { meters });
+        }
+        eprintln!("Working out walks along {}", path.display());
+        let meters: WalkMeters = CampusGraph::parse(&raw, path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?
+            .all_pairs(buildings);
+        if let Err(e) = write_json(&cached, &meters) {
+            eprintln!("Could not cache walks in {}: {}", cached.display(), e);
+        }
+        Ok(PathWalks { meters })
+    }
+}
+
+impl WalkModel for PathWalks {
+    fn seconds(&self, from: &str, to: &str, walk_speed: f32) -> Option<f32> {
+        Some(*self.meters.get(from)?.get(to)? / walk_speed)
+    }
+}
+
+//changed whenever walks are worked out differently, so walks cached before aren't used
+const WALKS_VERSION: &str = "2";
+
+///Where the walks for this graph and these buildings are cached
+fn cache_file(graph: &str, buildings: &BuildingMap, cache_dir: &Path) -> PathBuf {
+    let mut codes: Vec<(&String, &BuildingData)> = buildings.iter().collect();
+    codes.sort_by(|a, b| a.0.cmp(b.0));
+    let key: u64 = fnv1a(
+        WALKS_VERSION
+            .bytes()
+            .chain(graph.bytes())
+            .chain(format!("{:?}", codes).bytes()),
+    );
+    cache_dir.join("walks").join(format!("{:016x}.json", key))
+}
+
+///FNV-1a, which unlike the std hasher gives the same hash on every run and version
+fn fnv1a(bytes: impl Iterator<Item = u8>) -> u64 {
+    bytes.fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buildings() -> BuildingMap {
+        let raw: String = fs::read_to_string("cache/buildings.json").unwrap();
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    fn graph(raw: &str, file: &str) -> CampusGraph {
+        CampusGraph::parse(raw, Path::new(file)).unwrap()
+    }
+
+    //ESJ and PHY with a fence between them: the straight path is long to walk, the way around the
+    //mall isn't. EGR is only joined to the far corner, which no path reaches
+    const FENCED: &str = r#"{
+        "nodes": {
+            "ESJ": {"lat": 38.9867, "long": -76.94192},
+            "PHY": {"lat": 38.988697, "long": -76.94008},
+            "mall": {"lat": 38.9872, "long": -76.9410},
+            "EGR": {"lat": 38.98887, "long": -76.93797},
+            "corner": {"lat": 38.9890, "long": -76.9375}
+        },
+        "edges": [["ESJ", "PHY", 2000], ["ESJ", "mall", 150], ["mall", "PHY", 200], ["EGR", "corner"]]
+    }"#;
+
+    #[test]
+    fn detour_beats_a_blocked_straight_line() {
+        let walks: WalkMeters = graph(FENCED, "fenced.json").all_pairs(&buildings());
+        assert_eq!(walks["ESJ"]["PHY"], 350.0);
+        assert_eq!(walks["PHY"]["ESJ"], 350.0);
+        assert_eq!(walks["ESJ"]["ESJ"], 0.0);
+    }
+
+    #[test]
+    fn disconnected_pairs_are_left_out() {
+        let walks: WalkMeters = graph(FENCED, "fenced.json").all_pairs(&buildings());
+        assert!(!walks["ESJ"].contains_key("EGR"));
+        assert!(!walks["EGR"].contains_key("PHY"));
+        assert_eq!(walks["EGR"]["EGR"], 0.0);
+    }
+
+    #[test]
+    fn bad_lengths_are_errors_naming_the_path() {
+        for length in ["-5", "-0.5"] {
+            let raw: String = FENCED.replace("2000", length);
+            let error: String = CampusGraph::parse(&raw, Path::new("fenced.json"))
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains("path ESJ-PHY"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn geojson_lines_meet_at_a_shared_vertex() {
+        let raw: String = fs::read_to_string("fixtures/campus.geojson").unwrap();
+        let campus: CampusGraph = graph(&raw, "campus.geojson");
+        //the two lines share the point on the mall
+        assert_eq!(campus.nodes.len(), 5);
+        let walks: WalkMeters = campus.all_pairs(&buildings());
+        let esj_phy: f32 = walks["ESJ"]["PHY"];
+        assert!(esj_phy > 273.1 && esj_phy < 400.0, "{}", esj_phy);
+        assert!(walks["ESJ"]["EGR"] > esj_phy);
+    }
+
+    #[test]
+    fn geojson_entrances_join_the_nearest_path() {
+        let raw: String = fs::read_to_string("fixtures/campus.geojson").unwrap();
+        let campus: CampusGraph = graph(&raw, "campus.geojson");
+        let (node, walk_in) = campus.entrances["ESJ"];
+        //the entrance is off the line, not a point of its own
+        assert_eq!(campus.nodes.len(), 5);
+        assert!(!campus.edges[node].is_empty());
+        assert!(walk_in > 0.0 && walk_in < 10.0, "{}", walk_in);
+    }
+
+    #[test]
+    fn cache_file_changes_with_the_graph_and_buildings() {
+        let dir: &Path = Path::new("cache");
+        let mut buildings: BuildingMap = buildings();
+        let cached: PathBuf = cache_file(FENCED, &buildings, dir);
+        assert_eq!(cached, cache_file(FENCED, &buildings, dir));
+        assert!(cached.starts_with("cache/walks"));
+        assert_ne!(
+            cached,
+            cache_file(&FENCED.replace("150", "160"), &buildings, dir)
+        );
+        buildings.get_mut("ESJ").unwrap().lat += 0.001;
+        assert_ne!(cached, cache_file(FENCED, &buildings, dir));
+    }
+}
@@ -0,0 +1,392 @@
+use crate::buildings::UnknownBuildings;
+use crate::cache::{CacheMode, CourseCache, RatingCache};
+use crate::fetch::Fetcher;
+use crate::http::{HttpClient, HttpConfig, USER_AGENT};
+use crate::parallel::default_threads;
+use crate::profile::{MissingCourses, Profile, ProfileError};
+use crate::rating::{PLANETTERP_URL, RatingSpec};
+use crate::retry::RetryPolicy;
+use crate::source::{SourceSpec, UMD_IO_URL};
+use crate::structs::{Blockout, DayWindow, GenerateOptions, SoftConstraint};
+use crate::time::TimeOfDay;
+use crate::walk::WalkSpec;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::time::Duration;
+
+///Command line interface for the schedule maker
+#[derive(Debug, Parser)]
+#[command(name = "scheduler", about = "Generates schedules for UMD College Park")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    ///Generate schedules and print them in the chosen format
+    Generate(GenerateArgs),
+    ///Generate schedules and show them on the display page
+    Serve(GenerateArgs),
+    ///Fetch the sections of the given courses and print them as json
+    Fetch(FetchArgs),
+    ///Time schedule generation with and without the conflict graph (try -p profiles/engineering.toml)
+    Bench(BenchArgs),
+    ///Add a building to the building file, or move one that is already there
+    AddBuilding(AddBuildingArgs),
+}
+
+///Everything needed to run the schedule pipeline once. Anything left out comes from the profile (or the defaults)
+#[derive(Debug, Args, Clone)]
+pub struct GenerateArgs {
+    ///Profile file (.toml or .json) holding a student's full request
+    #[arg(short, long)]
+    pub profile: Option<PathBuf>,
+
+    ///Write the resolved request to this profile file (.toml or .json) before running
+    #[arg(long)]
+    pub save_profile: Option<PathBuf>,
+
+    ///Courses that must be in every schedule (e.g. -c PHYS260,ENES200)
+    #[arg(short, long = "course", value_delimiter = ',', required_unless_present = "profile")]
+    pub courses: Vec<String>,
+
+    ///Courses that can replace preferred courses as long as they don't overlap with required ones
+    #[arg(short, long = "alternate", value_delimiter = ',')]
+    pub alternates: Vec<String>,
+
+    ///What to do when a required course couldn't be fetched or has no sections [default: abort]
+    #[arg(long, value_enum)]
+    pub missing_courses: Option<MissingCourses>,
+
+    ///Semester id in YYYYMM form [default: 202601]
+    #[arg(short, long)]
+    pub semester: Option<String>,
+
+    ///Walk speed in meters per second [default: 1.42]
+    #[arg(long)]
+    pub walk_speed: Option<f32>,
+
+    ///Minutes early a student wants to get to their next class [default: 5]
+    #[arg(long)]
+    pub arrival_buffer: Option<u32>,
+
+    ///What to assume about a meeting in a building missing from the building file [default: worst-case]
+    #[arg(long, value_enum)]
+    pub unknown_buildings: Option<UnknownBuildings>,
+
+    ///Earliest time a class may start, as HHMM, HH:MM or 9:00am [default: 900]
+    #[arg(long)]
+    pub earliest: Option<TimeOfDay>,
+
+    ///Latest time a class may end, as HHMM, HH:MM or 5:00pm [default: 1700]
+    #[arg(long)]
+    pub latest: Option<TimeOfDay>,
+
+    ///Window for a single day as DAY:EARLIEST-LATEST, either time may be left blank (e.g. --day F:1000-)
+    #[arg(long = "day", value_parser = DayWindow::parse)]
+    pub days: Vec<(String, DayWindow)>,
+
+    ///Busy period no section may overlap, as DAYS:START-END[:LABEL][@BUILDING] (e.g. --blockout MWF:1200-1300:Lunch@STAMP)
+    #[arg(long = "blockout", value_parser = Blockout::parse)]
+    pub blockouts: Vec<Blockout>,
+
+    ///Window to prefer no classes in, as DAYS:START-END:WEIGHT[:LABEL] (e.g. --avoid-window MTuWThF:1130-1300:2:lunch)
+    #[arg(long = "avoid-window", value_parser = SoftConstraint::parse_window)]
+    pub avoid_windows: Vec<SoftConstraint>,
+
+    ///Day to prefer having off, as DAY:WEIGHT (e.g. --day-off F:3)
+    #[arg(long = "day-off", value_parser = SoftConstraint::parse_day_off)]
+    pub days_off: Vec<SoftConstraint>,
+
+    ///Building to prefer avoiding, as BUILDING:WEIGHT (e.g. --avoid-building ESJ:1)
+    #[arg(long = "avoid-building", value_parser = SoftConstraint::parse_avoid_building)]
+    pub avoid_buildings: Vec<SoftConstraint>,
+
+    ///Json file of custom sections to add [default: cache/custom.json]
+    #[arg(long)]
+    pub custom_sections: Option<PathBuf>,
+
+    ///Json file of building coordinates [default: cache/buildings.json]
+    #[arg(long)]
+    pub buildings: Option<PathBuf>,
+
+    ///How walking times are worked out: manhattan (north-south then east-west), haversine (straight line) or table:PATH (json of seconds between buildings) or paths:PATH (shortest walks along a .json or .geojson campus path graph) [default: manhattan]
+    #[arg(long, value_parser = WalkSpec::parse)]
+    pub walk_model: Option<WalkSpec>,
+
+    ///How generated schedules are printed
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    ///Only keep this many of the highest rated schedules, skipping the rest during the search
+    #[arg(long)]
+    pub top: Option<usize>,
+
+    ///Worker threads for generation and alternates (output is the same for any count) [default: all cores]
+    #[arg(short = 'j', long)]
+    pub threads: Option<usize>,
+
+    ///Print schedules as soon as they are found (json is one schedule per line), skipping alternates and rating order
+    #[arg(long)]
+    pub stream: bool,
+
+    #[command(flatten)]
+    pub data: DataArgs,
+}
+
+impl GenerateArgs {
+    ///How schedules should be generated for this run
+    pub fn options(&self) -> GenerateOptions {
+        GenerateOptions {
+            top: self.top,
+            threads: self.threads.unwrap_or_else(default_threads).max(1),
+        }
+    }
+
+    ///Loads the profile (if any) and lays the command line arguments over it
+    pub fn resolve_profile(&self) -> Result<Profile, ProfileError> {
+        let mut profile: Profile = match &self.profile {
+            Some(path) => Profile::load(path)?,
+            None => Profile::default(),
+        };
+
+        if !self.courses.is_empty() {
+            profile.courses = self.courses.clone();
+        }
+        if !self.alternates.is_empty() {
+            profile.alternates = self.alternates.clone();
+        }
+        if let Some(policy) = self.missing_courses {
+            profile.missing_courses = policy;
+        }
+        if let Some(semester) = &self.semester {
+            profile.semester = semester.clone();
+        }
+        if let Some(walk_speed) = self.walk_speed {
+            profile.constraints.walk_speed = walk_speed;
+        }
+        if let Some(minutes) = self.arrival_buffer {
+            profile.constraints.arrival_buffer = minutes;
+        }
+        if let Some(policy) = self.unknown_buildings {
+            profile.constraints.unknown_buildings = policy;
+        }
+        if let Some(earliest) = self.earliest {
+            profile.constraints.earliest = earliest;
+        }
+        if let Some(latest) = self.latest {
+            profile.constraints.latest = latest;
+        }
+        for (day, window) in &self.days {
+            profile.constraints.days.insert(day.clone(), *window);
+        }
+        profile
+            .constraints
+            .blockouts
+            .extend(self.blockouts.iter().cloned());
+        profile.constraints.soft.extend(
+            self.avoid_windows
+                .iter()
+                .chain(&self.days_off)
+                .chain(&self.avoid_buildings)
+                .cloned(),
+        );
+        if let Some(path) = &self.custom_sections {
+            profile.custom_sections = path.clone();
+        }
+        if let Some(path) = &self.buildings {
+            profile.buildings = path.clone();
+        }
+        if let Some(spec) = &self.walk_model {
+            profile.walk_model = spec.clone();
+        }
+
+        profile.validate()?;
+        if let Some(path) = &self.save_profile {
+            profile.save(path)?;
+        }
+        Ok(profile)
+    }
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct BenchArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    ///How many times to run each path
+    #[arg(long, default_value_t = 5)]
+    pub runs: usize,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct FetchArgs {
+    ///Courses to fetch
+    #[arg(value_delimiter = ',', required = true)]
+    pub courses: Vec<String>,
+
+    ///Semester id in YYYYMM form
+    #[arg(short, long, default_value = "202601")]
+    pub semester: String,
+
+    #[command(flatten)]
+    pub data: DataArgs,
+}
+
+///Where course data comes from, where it is cached and how the cache is used
+#[derive(Debug, Args, Clone)]
+pub struct DataArgs {
+    ///Where course sections come from: umd (the umd.io api), dir:PATH (umd.io or saved section json files as PATH/SEMESTER/COURSE.json) or file:PATH (saved fetch output)
+    #[arg(long, value_parser = SourceSpec::parse, default_value = "umd")]
+    pub source: SourceSpec,
+
+    ///Where professor ratings come from: planetterp, file:PATH (a json object or name,rating csv) or none
+    #[arg(long, value_parser = RatingSpec::parse, default_value = "planetterp")]
+    pub ratings: RatingSpec,
+
+    ///Base url of the umd.io api (e.g. a local fixture_server)
+    #[arg(long, env = "SCHEDULER_UMD_URL", default_value = UMD_IO_URL)]
+    pub umd_url: String,
+
+    ///Base url of the planetterp api
+    #[arg(long, env = "SCHEDULER_PLANETTERP_URL", default_value = PLANETTERP_URL)]
+    pub planetterp_url: String,
+
+    ///Times a failed request that may succeed later is tried again, with growing waits in between
+    #[arg(long, default_value_t = 3)]
+    pub retries: u32,
+
+    ///Most courses or professors looked up at once
+    #[arg(long, default_value_t = 10)]
+    pub concurrency: usize,
+
+    ///Requests per second sent to any one host, 0 for no limit
+    #[arg(long, default_value_t = 5.0)]
+    pub rate_limit: f64,
+
+    ///Seconds to wait for a connection to the api
+    #[arg(long, default_value_t = 10)]
+    pub connect_timeout: u64,
+
+    ///Seconds to wait for more of a response before giving up on the request
+    #[arg(long, default_value_t = 30)]
+    pub read_timeout: u64,
+
+    ///User agent sent with every request
+    #[arg(long, default_value = USER_AGENT)]
+    pub user_agent: String,
+
+    ///Only use cached courses, failing if one was never fetched
+    #[arg(long, conflicts_with = "refresh")]
+    pub offline: bool,
+
+    ///Fetch every course again, replacing what is cached
+    #[arg(long)]
+    pub refresh: bool,
+
+    ///Hours fetched courses are used before being fetched again
+    #[arg(long, default_value_t = 24)]
+    pub cache_ttl: u64,
+
+    ///Directory the cache is kept in
+    #[arg(long, default_value = "cache")]
+    pub cache_dir: PathBuf,
+}
+
+impl DataArgs {
+    fn mode(&self) -> CacheMode {
+        if self.offline {
+            CacheMode::Offline
+        } else if self.refresh {
+            CacheMode::Refresh
+        } else {
+            CacheMode::Normal
+        }
+    }
+
+    fn ttl(&self) -> Duration {
+        //a ttl too long to count in seconds never runs out anyway
+        Duration::from_secs(self.cache_ttl.saturating_mul(60 * 60))
+    }
+
+    fn http(&self) -> HttpConfig {
+        HttpConfig {
+            connect_timeout: Duration::from_secs(self.connect_timeout),
+            read_timeout: Duration::from_secs(self.read_timeout),
+            user_agent: self.user_agent.clone(),
+            rate_limit: self.rate_limit,
+        }
+    }
+
+    ///Where data from the api at `url` is cached. The real api uses the cache directory itself,
+    ///anything else (like a fixture_server) gets a directory of its own so the two never mix
+    fn cache_dir_for(&self, url: &str, real_url: &str) -> PathBuf {
+        let url: &str = url.trim_end_matches('/');
+        if url == real_url {
+            return self.cache_dir.clone();
+        }
+        let host: String = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.cache_dir.join("hosts").join(host)
+    }
+
+    ///Opens the course source and rating provider along with their caches, sharing one http client.
+    ///Professor ratings are kept in one file for every course and semester
+    pub fn fetcher(&self) -> Result<Fetcher, Box<dyn std::error::Error>> {
+        let http: HttpClient = HttpClient::new(&self.http())?;
+        Ok(Fetcher {
+            source: self.source.open(&self.umd_url, &http)?,
+            ratings: self.ratings.open(&self.planetterp_url, &http)?,
+            cache: CourseCache::new(
+                self.cache_dir_for(&self.umd_url, UMD_IO_URL),
+                self.ttl(),
+                self.mode(),
+            ),
+            rating_cache: RatingCache::load(
+                self.cache_dir_for(&self.planetterp_url, PLANETTERP_URL)
+                    .join("ratings.json"),
+                self.ttl(),
+                self.mode(),
+            ),
+            retry: RetryPolicy::new(self.retries),
+            concurrency: self.concurrency.max(1),
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct AddBuildingArgs {
+    ///Building code as umd.io gives it (e.g. ESJ)
+    pub code: String,
+
+    ///Latitude of the building's entrance
+    #[arg(allow_negative_numbers = true)]
+    pub lat: f64,
+
+    ///Longitude of the building's entrance
+    #[arg(allow_negative_numbers = true)]
+    pub long: f64,
+
+    ///Json file of building coordinates
+    #[arg(long, default_value = "cache/buildings.json")]
+    pub buildings: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    ///Plain text list of schedules, same layout as viable.txt
+    Text,
+    ///Display schedules as json
+    Json,
+}
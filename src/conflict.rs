@@ -0,0 +1,305 @@
+use crate::schedule::is_conflict;
+use crate::structs::*;
+use std::collections::BTreeMap;
+
+///Indexes of sections in a ConflictGraph, one per course
+pub type SectionIds = Vec<usize>;
+
+///Fixed size set of section indexes, one bit per section
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(len: usize) -> BitSet {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    pub fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    ///Adds every member of other to this set
+    pub fn union_with(&mut self, other: &BitSet) {
+        for (word, other_word) in self.words.iter_mut().zip(&other.words) {
+            *word |= other_word;
+        }
+    }
+
+    ///True when some member of this set is not in other
+    pub fn has_any_outside(&self, other: &BitSet) -> bool {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .any(|(word, other_word)| word & !other_word != 0)
+    }
+
+    ///How many members of this set are not in other
+    pub fn count_outside(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(word, other_word)| (word & !other_word).count_ones() as usize)
+            .sum()
+    }
+
+    ///Members of this set that are not in other, smallest first
+    pub fn iter_outside<'a>(&'a self, other: &'a BitSet) -> impl Iterator<Item = usize> + 'a {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .enumerate()
+            .flat_map(|(w, (word, other_word))| {
+                let mut bits: u64 = word & !other_word;
+                std::iter::from_fn(move || {
+                    if bits == 0 {
+                        return None;
+                    }
+                    let bit: usize = bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    Some(w * 64 + bit)
+                })
+            })
+    }
+}
+
+///Every desired and alternate section, indexed, with which pairs can't be taken together.
+///Built once per run so generation and alternates never conflict check the same pair twice
+pub struct ConflictGraph {
+    sections: Vec<Section>,
+    //section indexes of every usable section, by course
+    courses: BTreeMap<String, Vec<usize>>,
+    desired: Vec<String>,
+    alternates: Vec<String>,
+    //adjacency[a] holds every section that conflicts with section a
+    adjacency: Vec<BitSet>,
+}
+
+impl ConflictGraph {
+    ///Indexes the usable sections of the inputs and conflict checks every pair from different courses
+    pub fn new(inputs: &ScheduleInputs, constraints: &Constraints) -> ConflictGraph {
+        let mut sections: Vec<Section> = Vec::new();
+        let mut courses: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for course_map in [&inputs.desired_courses, &inputs.alternate_courses] {
+            for (course, section_map) in course_map {
+                let mut course_sections: Vec<&Section> = section_map
+                    .values()
+                    .filter(|s| s.is_allowed(inputs.walk.as_ref(), constraints))
+                    .collect();
+                //sorting makes the process deterministic for testing
+                course_sections.sort_by(|a, b| a.section.cmp(&b.section));
+                let ids: &mut Vec<usize> = courses.entry(course.clone()).or_default();
+                for section in course_sections {
+                    ids.push(sections.len());
+                    sections.push(section.clone());
+                }
+            }
+        }
+
+        let mut adjacency: Vec<BitSet> = vec![BitSet::new(sections.len()); sections.len()];
+        let course_ids: Vec<&Vec<usize>> = courses.values().collect();
+        for (i, ids) in course_ids.iter().enumerate() {
+            for other_ids in &course_ids[i + 1..] {
+                for &a in ids.iter() {
+                    for &b in other_ids.iter() {
+                        if is_conflict(&sections[a], &sections[b], inputs.walk.as_ref(), constraints)
+                        {
+                            adjacency[a].insert(b);
+                            adjacency[b].insert(a);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut desired: Vec<String> = inputs.desired_courses.keys().cloned().collect();
+        desired.sort();
+        let mut alternates: Vec<String> = inputs.alternate_courses.keys().cloned().collect();
+        alternates.sort();
+
+        ConflictGraph {
+            sections,
+            courses,
+            desired,
+            alternates,
+            adjacency,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    pub fn section(&self, id: usize) -> &Section {
+        &self.sections[id]
+    }
+
+    ///Usable sections of the course, ordered by section code
+    pub fn course_sections(&self, course: &str) -> &[usize] {
+        self.courses.get(course).map(|ids| ids.as_slice()).unwrap_or(&[])
+    }
+
+    ///Required courses, alphabetically
+    pub fn desired_courses(&self) -> &[String] {
+        &self.desired
+    }
+
+    ///Alternate courses, alphabetically
+    pub fn alternate_courses(&self) -> &[String] {
+        &self.alternates
+    }
+
+    ///Everything that can't be taken with the section
+    pub fn conflicts_of(&self, id: usize) -> &BitSet {
+        &self.adjacency[id]
+    }
+
+    pub fn conflicts(&self, a: usize, b: usize) -> bool {
+        self.adjacency[a].contains(b)
+    }
+
+    ///Copies the sections out of the graph, sorted alphabetically by course
+    pub fn schedule(&self, ids: &[usize]) -> Schedule {
+        let mut schedule: Schedule = ids.iter().map(|&id| self.sections[id].clone()).collect();
+        schedule.sort_by(|a, b| a.course.cmp(&b.course));
+        schedule
+    }
+
+    ///Finds the alternate sections that can replace `replacing` in the given schedule
+    pub fn find_alt(&self, schedule: &[usize], replacing: usize) -> Vec<usize> {
+        //everything that conflicts with the rest of the schedule
+        let mut blocked: BitSet = BitSet::new(self.len());
+        for &id in schedule.iter().filter(|&&id| id != replacing) {
+            blocked.union_with(&self.adjacency[id]);
+        }
+
+        self.alternates
+            .iter()
+            .flat_map(|course| self.course_sections(course))
+            .copied()
+            .filter(|&id| !blocked.contains(id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::walk::Manhattan;
+    use std::fs;
+    use std::sync::Arc;
+
+    ///Made up courses in fixtures/sections.json: four required ones and two alternates, with
+    ///overlaps, walks too long to make, Friday meetings and a building missing from the building file
+    pub(crate) fn sample_inputs() -> ScheduleInputs {
+        let raw: String = fs::read_to_string("fixtures/sections.json").unwrap();
+        let mut desired_courses: CourseMap = serde_json::from_str(&raw).unwrap();
+        let alternate_courses: CourseMap = ["EEE110", "FFF120"]
+            .into_iter()
+            .map(|course| desired_courses.remove_entry(course).unwrap())
+            .collect();
+        let raw: String = fs::read_to_string("cache/buildings.json").unwrap();
+        let buildings: BuildingMap = serde_json::from_str(&raw).unwrap();
+        ScheduleInputs {
+            desired_courses,
+            alternate_courses,
+            walk: Arc::new(Manhattan::new(buildings.clone())),
+            buildings,
+        }
+    }
+
+    ///The default constraints with one soft constraint of every kind
+    pub(crate) fn sample_constraints() -> Constraints {
+        Constraints {
+            soft: vec![
+                SoftConstraint::parse_window("MWF:12:00-13:00:1:lunch").unwrap(),
+                SoftConstraint::parse_day_off("F:2").unwrap(),
+                SoftConstraint::parse_avoid_building("EGR:0.5").unwrap(),
+            ],
+            ..Constraints::default()
+        }
+    }
+
+    #[test]
+    fn adjacency_matches_is_conflict() {
+        let (inputs, constraints) = (sample_inputs(), sample_constraints());
+        let graph: ConflictGraph = ConflictGraph::new(&inputs, &constraints);
+        let mut conflicts: usize = 0;
+        for a in 0..graph.len() {
+            for b in 0..graph.len() {
+                let (first, second) = (graph.section(a), graph.section(b));
+                //sections of one course are never taken together, so they aren't checked
+                let expected: bool = first.course != second.course
+                    && is_conflict(first, second, inputs.walk.as_ref(), &constraints);
+                assert_eq!(graph.conflicts(a, b), expected, "{:?} {:?}", first, second);
+                conflicts += expected as usize;
+            }
+        }
+        //the fixture has to exercise both answers
+        assert!(conflicts > 0 && conflicts < graph.len() * graph.len() / 2);
+    }
+
+    #[test]
+    fn find_alt_matches_is_conflict() {
+        let (inputs, constraints) = (sample_inputs(), sample_constraints());
+        let graph: ConflictGraph = ConflictGraph::new(&inputs, &constraints);
+        let walk = inputs.walk.as_ref();
+        let alternates: Vec<usize> = graph
+            .alternate_courses()
+            .iter()
+            .flat_map(|course| graph.course_sections(course))
+            .copied()
+            .collect();
+        let schedules: Vec<SectionIds> = crate::schedule::get_potential_schedules(&graph);
+        assert!(!schedules.is_empty());
+        for schedule in &schedules {
+            for &replacing in schedule {
+                let expected: Vec<usize> = alternates
+                    .iter()
+                    .copied()
+                    .filter(|&alt| {
+                        schedule.iter().filter(|&&id| id != replacing).all(|&id| {
+                            !is_conflict(graph.section(alt), graph.section(id), walk, &constraints)
+                        })
+                    })
+                    .collect();
+                assert_eq!(graph.find_alt(schedule, replacing), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn only_allowed_sections_are_indexed() {
+        let inputs: ScheduleInputs = sample_inputs();
+        let constraints: Constraints = Constraints {
+            blockouts: vec![Blockout::parse("TuTh:9:00-10:00").unwrap()],
+            ..sample_constraints()
+        };
+        let graph: ConflictGraph = ConflictGraph::new(&inputs, &constraints);
+        let codes = |course: &str| -> Vec<&str> {
+            graph
+                .course_sections(course)
+                .iter()
+                .map(|&id| graph.section(id).section.as_str())
+                .collect()
+        };
+        assert_eq!(codes("AAA100"), ["0101", "0102", "0202"]);
+        assert_eq!(codes("DDD400"), ["0201"]);
+        assert_eq!(
+            graph.desired_courses(),
+            ["AAA100", "BBB200", "CCC300", "DDD400"]
+        );
+        assert_eq!(graph.alternate_courses(), ["EEE110", "FFF120"]);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::structs::SectionMap;
+use reqwest::{Response, StatusCode, header::RETRY_AFTER};
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub type FetchResult = Result<SectionMap, FetchError>;
+
+///Why a course's sections or a professor's rating couldn't be fetched
+#[derive(Debug)]
+pub enum FetchError {
+    ///The request never got a response (timeouts, refused connections, dropped bodies)
+    Network(reqwest::Error),
+    ///The server answered with an unsuccessful status
+    Status(StatusCode),
+    ///The server asked us to slow down, maybe saying for how long
+    RateLimited(Option<Duration>),
+    ///The source has no such course in the semester (course, semester)
+    UnknownCourse(String, String),
+    ///The response didn't have the expected shape
+    Malformed {
+        //what was being read, e.g. umd.io sections of PHYS260
+        what: String,
+        //path to the offending field, e.g. [2].meetings[0].start_time
+        field: String,
+        message: String,
+    },
+    ///Running offline and the course was never cached for the semester
+    NotCached(String),
+    ///A local source couldn't be read
+    Io(PathBuf, std::io::Error),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Network(e) if e.is_timeout() => write!(f, "timed out: {}", e),
+            FetchError::Network(e) => write!(f, "network error: {}", e),
+            FetchError::Status(status) => write!(f, "unexpected http status {}", status),
+            FetchError::RateLimited(Some(wait)) => {
+                write!(f, "rate limited, asked to wait {}s", wait.as_secs())
+            }
+            FetchError::RateLimited(None) => write!(f, "rate limited"),
+            FetchError::UnknownCourse(course, semester) => {
+                write!(f, "no course {} in semester {}", course, semester)
+            }
+            FetchError::Malformed {
+                what,
+                field,
+                message,
+            } => write!(f, "malformed {} at {}: {}", what, field, message),
+            FetchError::NotCached(semester) => {
+                write!(f, "not cached for semester {} (offline)", semester)
+            }
+            FetchError::Io(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        FetchError::Network(e)
+    }
+}
+
+impl FetchError {
+    ///Passes successful responses through and turns the rest into a Status or RateLimited error
+    pub fn check(response: Response) -> Result<Response, FetchError> {
+        let status: StatusCode = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            //only the seconds form of Retry-After is used, dates are left to the backoff
+            let retry_after: Option<Duration> = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
+            return Err(FetchError::RateLimited(retry_after));
+        }
+        Err(FetchError::Status(status))
+    }
+
+    ///Parses json, naming the offending field when it doesn't have the expected shape
+    pub fn parse<T: DeserializeOwned>(raw: &str, what: &str) -> Result<T, FetchError> {
+        let deserializer = &mut serde_json::Deserializer::from_str(raw);
+        serde_path_to_error::deserialize(deserializer).map_err(|e| FetchError::Malformed {
+            what: what.to_string(),
+            field: e.path().to_string(),
+            message: e.into_inner().to_string(),
+        })
+    }
+
+    ///Errors that may go away are retried: timeouts, dropped connections, 5xx and 429.
+    ///Unknown courses, malformed payloads and missing files would just fail again
+    pub fn retry(&self) -> Retry {
+        match self {
+            FetchError::Network(e) if e.is_builder() || e.is_redirect() => Retry::Never,
+            FetchError::Network(_) => Retry::Backoff,
+            FetchError::RateLimited(Some(wait)) => Retry::After(*wait),
+            FetchError::RateLimited(None) => Retry::Backoff,
+            FetchError::Status(status)
+                if status.is_server_error() || *status == StatusCode::REQUEST_TIMEOUT =>
+            {
+                Retry::Backoff
+            }
+            _ => Retry::Never,
+        }
+    }
+}
+
+///Whether a failed request is worth trying again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Retry {
+    ///Try again after the policy's backoff
+    Backoff,
+    ///Try again after the time the server asked for
+    After(Duration),
+    ///Trying again would fail the same way
+    Never,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, retry_after: Option<&str>) -> Response {
+        let mut response = axum::http::Response::builder().status(status);
+        if let Some(retry_after) = retry_after {
+            response = response.header(RETRY_AFTER, retry_after);
+        }
+        Response::from(response.body("").unwrap())
+    }
+
+    fn checked(status: u16, retry_after: Option<&str>) -> Retry {
+        FetchError::check(response(status, retry_after))
+            .unwrap_err()
+            .retry()
+    }
+
+    #[test]
+    fn retry_after_seconds_are_waited_for() {
+        assert_eq!(
+            checked(429, Some("7")),
+            Retry::After(Duration::from_secs(7))
+        );
+        assert_eq!(checked(429, Some(" 0 ")), Retry::After(Duration::ZERO));
+        //dates and nonsense fall back to the backoff
+        assert_eq!(
+            checked(429, Some("Wed, 21 Oct 2026 07:28:00 GMT")),
+            Retry::Backoff
+        );
+        assert_eq!(checked(429, Some("soon")), Retry::Backoff);
+        assert_eq!(checked(429, None), Retry::Backoff);
+    }
+
+    #[test]
+    fn server_errors_are_retried() {
+        for status in [500, 502, 503, 504, 408] {
+            assert_eq!(checked(status, None), Retry::Backoff, "{}", status);
+        }
+    }
+
+    #[test]
+    fn errors_that_would_repeat_are_not_retried() {
+        for status in [400, 401, 403, 404, 410] {
+            assert_eq!(checked(status, None), Retry::Never, "{}", status);
+        }
+        let malformed: FetchError =
+            FetchError::parse::<SectionMap>("[1, 2]", "sections").unwrap_err();
+        let unknown: FetchError =
+            FetchError::UnknownCourse(String::from("PHYS999"), String::from("202601"));
+        let missing: FetchError = FetchError::Io(
+            PathBuf::from("fixtures/nothing.json"),
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+        );
+        let offline: FetchError = FetchError::NotCached(String::from("202601"));
+        for error in [malformed, unknown, missing, offline] {
+            assert_eq!(error.retry(), Retry::Never, "{}", error);
+        }
+    }
+
+    #[test]
+    fn successes_pass_through() {
+        assert!(FetchError::check(response(200, None)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn network_errors_are_retried_unless_the_request_is_bad() {
+        let client: reqwest::Client = reqwest::Client::new();
+        //nothing listens on port 1
+        let refused: FetchError = client
+            .get("http://127.0.0.1:1/")
+            .send()
+            .await
+            .unwrap_err()
+            .into();
+        assert_eq!(refused.retry(), Retry::Backoff, "{}", refused);
+        let bad_url: FetchError = client.get("not a url").send().await.unwrap_err().into();
+        assert_eq!(bad_url.retry(), Retry::Never, "{}", bad_url);
+    }
+
+    #[test]
+    fn malformed_payloads_name_the_field() {
+        let raw: &str = r#"{"0101": {"course": 5}}"#;
+        match FetchError::parse::<SectionMap>(raw, "sections of PHYS260") {
+            Err(FetchError::Malformed { what, field, .. }) => {
+                assert_eq!(what, "sections of PHYS260");
+                assert_eq!(field, "0101.course");
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -1,178 +1,276 @@
-use crate::schedule::{get_days, to_military};
+use crate::cache::{CacheMode, CourseCache, RatingCache};
+use crate::error::{FetchError, FetchResult};
+use crate::rating::{RatingProvider, RatingResult};
+use crate::retry::RetryPolicy;
+use crate::source::CourseSource;
 use crate::structs::*;
 use futures::{StreamExt, stream};
-use reqwest::Client;
-use std::collections::HashMap;
-const CONCURRENCY: usize = 10;
-
-///returns the sectionmap for a given course and semester from the UMD API
-pub async fn get_sections(
-    course_id: &String,
-    semester_id: &String,
-) -> Result<SectionMap, Box<dyn std::error::Error>> {
-    let client: Client = Client::new();
-    let raw: String = client
-        .get(format!(
-            "https://api.umd.io/v1/courses/{}/sections?semester={}",
-            course_id, semester_id
-        ))
-        .send()
-        .await?
-        .text()
-        .await?;
-    //collection data into the input struct
-    let input_list: Vec<SectionInput> = serde_json::from_str(&raw)?;
-    let mut output_map: SectionMap = HashMap::new();
-
-    //map for storing professor ratings
-    let mut rating_map: HashMap<String, f32> = HashMap::new();
-
-    //format it into the output
-    for section_input in input_list {
-        //iterate through each section
-        //format the Classtimes struct
-        let mut classtimes: Classtimes = HashMap::new();
-        for meeting in section_input.meetings {
-            //iterate through each meeting group
-            // format the start and end time of this meet group
-            let start_end: StartEnd = StartEnd {
-                building: meeting.building,
-                start: to_military(meeting.start_time),
-                end: to_military(meeting.end_time),
-            };
-            //get the days where this meet group occurs
-            let days: Vec<u32> = get_days(meeting.days);
-            //use the times and days to add an entries to the classtimes property for this section
-            for day in days {
-                //classtimes.insert(day, start_end.clone());
-                if let Some(day_meetings) = classtimes.get_mut(&day) {
-                    day_meetings.push(start_end.clone());
-                } else {
-                    classtimes.insert(day, vec![start_end.clone()]);
-                }
-            }
-        }
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-        let course_name: String = section_input.course;
-        let section_name: String = section_input.number;
-        let professor: String = match section_input.instructors.is_empty() {
-            true => String::from("Unknown"),
-            false => section_input.instructors[0].clone(),
-        };
-        let seats: [u32; 3] = [
-            section_input.seats.parse().unwrap_or_default(),
-            section_input.open_seats.parse().unwrap_or_default(),
-            section_input.waitlist.parse().unwrap_or_default(),
-        ];
-
-        //get professor rating data
-        let rating: f32;
-        if professor == "Unknown" {
-            rating = 0.0;
-        } else if let Some(r) = rating_map.get(&professor) {
-            rating = *r;
-        } else {
-            let (firstname, lastname) = professor.split_once(' ').unwrap_or_default();
-            let mut tries: u8 = 0;
-            loop {
-                let raw: Option<String> = async {
-                    Some(
-                        client
-                            .get(format!(
-                            "https://planetterp.com/api/v1/professor?name={}%20{}",
-                            firstname, lastname
-                            ))
-                            .send()
-                            .await.ok()?
-                            .text()
-                            .await.ok()?
-                    )
-                }
-                .await;
-                if let Some(data) = raw {
-                    let prof_data: ProfRatingInput = serde_json::from_str(&data).unwrap_or_default();
-                    if prof_data != ProfRatingInput::default() {
-                        rating = prof_data.average_rating;
-                        rating_map.insert(professor.clone(), rating);
-                        println!(
-                            "Retrieved rating {} for professor {}",
-                            rating, professor
-                        );
-                        break;
-                    }
+///What a fetch got and what it didn't, for the command line and the display page to show
+#[derive(Debug, Default)]
+pub struct FetchReport {
+    pub courses: CourseMap,
+    //courses that couldn't be fetched, and why
+    pub failed_courses: BTreeMap<String, FetchError>,
+    //professors whose rating lookup failed. Professors the provider doesn't know aren't failures
+    pub failed_ratings: BTreeMap<String, FetchError>,
+}
+
+impl FetchReport {
+    ///One line per failure, courses first
+    pub fn problems(&self) -> Vec<String> {
+        let courses = self
+            .failed_courses
+            .iter()
+            .map(|(course, e)| format!("Could not get course {}: {}", course, e));
+        let ratings = self
+            .failed_ratings
+            .iter()
+            .map(|(professor, e)| format!("Could not get a rating for {}: {}", professor, e));
+        courses.chain(ratings).collect()
+    }
+}
+
+///Everything needed to fetch courses: where sections and ratings come from and where they are cached
+pub struct Fetcher {
+    pub source: Box<dyn CourseSource>,
+    pub ratings: Box<dyn RatingProvider>,
+    pub cache: CourseCache,
+    pub rating_cache: RatingCache,
+    //used for both sections and ratings
+    pub retry: RetryPolicy,
+    //most courses or professors looked up at once
+    pub concurrency: usize,
+}
+
+impl Fetcher {
+    ///Fetches all courses from the source concurrently.
+    ///Courses found in the cache are used as is, everything fetched is added to it.
+    ///Ratings are looked up once every section is in, one lookup per professor
+    pub async fn fetch_all_courses(&self, ideal_courses: &[String], semester: &str) -> FetchReport {
+        let results: Vec<(String, FetchResult)> = stream::iter(ideal_courses.iter().cloned())
+            .map(|course: String| async move {
+                let res: FetchResult = self.course(&course, semester).await;
+                (course, res)
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+
+        let mut report: FetchReport = FetchReport::default();
+        for (course, secs_res) in results {
+            match secs_res {
+                Ok(secs) => {
+                    report.courses.insert(course, secs);
                 }
-                tries += 1;
-                if tries >= 3 {
-                    rating = 0.0;
-                    rating_map.insert(professor.clone(), rating);
-                    println!(
-                        "Could not retrieve rating for professor {} after 3 tries",
-                        professor
-                    );
-                    break;
+                Err(e) => {
+                    report.failed_courses.insert(course, e);
                 }
             }
         }
 
+        self.rate_sections(&mut report).await;
+        report
+    }
 
-        //compile section struct, formatted for output
-        let section_formatted: Section = Section {
-            professor: ProfData {
-                name: professor,
-                rating: rating,
-            },
-            classtimes: classtimes,
-            course: course_name,
-            section: section_name.clone(),
-            seats: seats,
-        };
-        output_map.insert(section_name, section_formatted);
+    ///One course's sections from the cache, or from the source when they aren't cached
+    async fn course(&self, course: &str, semester: &str) -> FetchResult {
+        let cacheable: bool = self.source.cacheable();
+        if cacheable && let Some((secs, age)) = self.cache.load(course, semester) {
+            eprintln!(
+                "Using cached course {} (fetched {} minute(s) ago)",
+                course,
+                age.as_secs() / 60
+            );
+            return Ok(secs);
+        }
+        if cacheable && self.cache.mode == CacheMode::Offline {
+            return Err(FetchError::NotCached(semester.to_string()));
+        }
+
+        let res: FetchResult = self
+            .retry
+            .run(&format!("course {}", course), || {
+                self.source.sections(course, semester)
+            })
+            .await;
+        if res.is_ok() {
+            eprintln!("Successfully retrieved course {}", course);
+        }
+        //sections are cached as the source gave them, ratings are cached on their own
+        if cacheable && let Ok(secs) = &res {
+            self.cache.store(course, semester, secs);
+        }
+        //return the final result (either success or failure)
+        res
     }
 
-    return Ok(output_map);
-}
+    ///Fills in the rating of every professor that doesn't have one yet, looking each of them up once
+    async fn rate_sections(&self, report: &mut FetchReport) {
+        let professors: BTreeSet<String> = report
+            .courses
+            .values()
+            .flat_map(|sections| sections.values())
+            .flat_map(|s| &s.professors)
+            .filter(|p| p.rating == 0.0)
+            .map(|p| p.name.clone())
+            .collect();
 
-///Fetches all courses concurrently and returns a CourseMap
-pub async fn fetch_all_courses(ideal_courses: &[String], semester: &String) -> CourseMap {
-    let results: Vec<(String, Result<SectionMap, Box<dyn std::error::Error>>)> =
-        stream::iter(ideal_courses.iter().cloned())
-            .map(|course: String| {
-                let sem = semester.clone();
-                async move {
-                    let res = get_sections(&course, &sem).await;
-                    //retry up to 3 times if there was an error
-                    let mut count: i8 = 0;
-                    while res.is_err() && count < 3 {
-                        count += 1;
-                        //println!(
-                        //    "Retrying fetch for course {} (attempt {})",
-                        //    course,
-                        //    count + 1
-                        //);
-                        let res_retry = get_sections(&course, &sem).await;
-                        if res_retry.is_ok() {
-                            return (course, res_retry);
-                        }
-                    }
-                    //return the final result (either success or failure)
-                    (course, res)
-                }
+        let results: Vec<(String, RatingResult)> = stream::iter(professors)
+            .map(|professor: String| async move {
+                let res: RatingResult = self.rating(&professor).await;
+                (professor, res)
             })
-            .buffer_unordered(CONCURRENCY)
+            .buffer_unordered(self.concurrency)
             .collect()
             .await;
+        self.rating_cache.save();
 
-    let mut all_courses: CourseMap = HashMap::new();
-    for (course, secs_res) in results {
-        match secs_res {
-            Ok(secs) => {
-                println!("Successfully retrieved course {}", course);
-                all_courses.insert(course, secs);
+        let mut ratings: HashMap<String, f32> = HashMap::new();
+        for (professor, res) in results {
+            match res {
+                Ok(Some(rating)) => {
+                    ratings.insert(professor, rating);
+                }
+                //the provider doesn't know them, which leaves them unrated like unknown professors
+                Ok(None) => {}
+                Err(e) => {
+                    report.failed_ratings.insert(professor, e);
+                }
             }
-            Err(e) => {
-                println!("Error retrieving course {}: {}", course, e);
+        }
+
+        for professor in report
+            .courses
+            .values_mut()
+            .flat_map(|sections| sections.values_mut())
+            .flat_map(|s| s.professors.iter_mut())
+        {
+            if let Some(rating) = ratings.get(&professor.name) {
+                professor.rating = *rating;
             }
         }
     }
-    all_courses
+
+    ///One professor's rating from the rating cache, or from the provider with retries
+    async fn rating(&self, professor: &str) -> RatingResult {
+        let cacheable: bool = self.ratings.cacheable();
+        if cacheable && let Some(rating) = self.rating_cache.get(professor) {
+            return Ok(rating);
+        }
+        //offline, professors that were never looked up just go unrated
+        if cacheable && self.rating_cache.mode == CacheMode::Offline {
+            return Ok(None);
+        }
+
+        let res: RatingResult = self
+            .retry
+            .run(&format!("rating for {}", professor), || {
+                self.ratings.rating(professor)
+            })
+            .await;
+        if let Ok(rating) = res {
+            if let Some(rating) = rating {
+                eprintln!("Retrieved rating {} for professor {}", rating, professor);
+            }
+            //professors the provider doesn't know are remembered too, so they aren't asked about every run
+            if cacheable {
+                self.rating_cache.insert(professor, rating);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rating::StaticRatings;
+    use crate::schedule::build_display_schedules;
+    use crate::source::MockSource;
+    use crate::walk::Haversine;
+    use serde_json::json;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn courses() -> CourseMap {
+        serde_json::from_value(json!({
+            "PHYS260": {
+                "0101": {
+                    "professors": [{"name": "Ada Lovelace", "rating": 0.0}],
+                    "classtimes": {"M": [{"building": "PHY", "start": "10:00", "end": "10:50"}]},
+                    "course": "PHYS260", "section": "0101", "seats": [30, 5, 0]
+                }
+            },
+            "ENES200": {
+                "0101": {
+                    "professors": [
+                        {"name": "Alan Turing", "rating": 0.0},
+                        {"name": "Staff", "rating": 0.0}
+                    ],
+                    "classtimes": {"M": [{"building": "EGR", "start": "13:00", "end": "13:50"}]},
+                    "course": "ENES200", "section": "0101", "seats": [40, 0, 3]
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn fetcher() -> Fetcher {
+        let ratings: HashMap<String, f32> = HashMap::from([
+            ("Ada Lovelace".to_string(), 4.5),
+            ("Alan Turing".to_string(), 3.0),
+        ]);
+        //neither the mock source nor static ratings are cached, so the cache directory is never used
+        let cache_dir: PathBuf = std::env::temp_dir().join("scheduler-fetch-test");
+        Fetcher {
+            source: Box::new(MockSource::new(courses())),
+            ratings: Box::new(StaticRatings::new(ratings)),
+            cache: CourseCache::new(cache_dir.clone(), Duration::ZERO, CacheMode::Normal),
+            rating_cache: RatingCache::load(
+                cache_dir.join("ratings.json"),
+                Duration::ZERO,
+                CacheMode::Normal,
+            ),
+            retry: RetryPolicy::new(0),
+            concurrency: 2,
+        }
+    }
+
+    #[tokio::test]
+    async fn mock_courses_are_fetched_rated_and_scheduled() {
+        let requested: Vec<String> = ["PHYS260", "ENES200", "NOPE101"].map(String::from).to_vec();
+        let report: FetchReport = fetcher().fetch_all_courses(&requested, "202601").await;
+
+        assert_eq!(
+            report.courses.keys().collect::<Vec<_>>(),
+            ["ENES200", "PHYS260"]
+        );
+        assert!(matches!(
+            report.failed_courses.get("NOPE101"),
+            Some(FetchError::UnknownCourse(..))
+        ));
+        //a professor the ratings don't know stays unrated without counting as a failure
+        assert!(report.failed_ratings.is_empty());
+        assert_eq!(report.courses["PHYS260"]["0101"].professor_rating(), 4.5);
+        assert_eq!(report.courses["ENES200"]["0101"].professor_rating(), 3.0);
+
+        let inputs: ScheduleInputs = ScheduleInputs {
+            desired_courses: report.courses,
+            alternate_courses: CourseMap::new(),
+            buildings: BuildingMap::new(),
+            walk: Arc::new(Haversine::new(BuildingMap::new())),
+        };
+        let options: GenerateOptions = GenerateOptions {
+            top: None,
+            threads: 1,
+        };
+        let schedules: Vec<DisplaySchedule> =
+            build_display_schedules(&inputs, &Constraints::default(), &options);
+        assert_eq!(schedules.len(), 1);
+        let sections: Vec<&str> = schedules[0].iter().map(|s| s.course.as_str()).collect();
+        assert_eq!(sections, ["ENES200", "PHYS260"]);
+    }
 }
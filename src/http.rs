@@ -0,0 +1,99 @@
+use reqwest::{Client, Response, Url};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+pub const USER_AGENT: &str = concat!("umd-schedule-maker/", env!("CARGO_PKG_VERSION"));
+
+///How requests to the course and rating apis are made
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpConfig {
+    pub connect_timeout: Duration,
+    //longest wait for the next bit of a response, so a hung request fails instead of stalling the run
+    pub read_timeout: Duration,
+    pub user_agent: String,
+    //requests per second to any one host, 0 for no limit
+    pub rate_limit: f64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            user_agent: USER_AGENT.to_string(),
+            rate_limit: 5.0,
+        }
+    }
+}
+
+///One pooled client shared by every source and provider, with requests to each host spaced out.
+///Cloning is cheap and clones share both the connections and the rate limit
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Client,
+    limiter: Arc<HostLimiter>,
+}
+
+impl HttpClient {
+    pub fn new(config: &HttpConfig) -> Result<HttpClient, reqwest::Error> {
+        let client: Client = Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .read_timeout(config.read_timeout)
+            .user_agent(config.user_agent.as_str())
+            .build()?;
+        let interval: Duration = match config.rate_limit {
+            limit if limit > 0.0 => Duration::from_secs_f64(1.0 / limit),
+            _ => Duration::ZERO,
+        };
+        Ok(HttpClient {
+            client,
+            limiter: Arc::new(HostLimiter::new(interval)),
+        })
+    }
+
+    ///Sends a GET once the url's host is allowed another request
+    pub async fn get(&self, url: &str) -> Result<Response, reqwest::Error> {
+        if let Ok(parsed) = Url::parse(url) {
+            self.limiter.wait(&parsed).await;
+        }
+        self.client.get(url).send().await
+    }
+}
+
+///Spaces requests to the same host at least `interval` apart, in the order they asked
+struct HostLimiter {
+    interval: Duration,
+    //when each host may next be sent a request
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostLimiter {
+    fn new(interval: Duration) -> HostLimiter {
+        HostLimiter {
+            interval,
+            next: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn wait(&self, url: &Url) {
+        if self.interval.is_zero() {
+            return;
+        }
+        let host: String = format!(
+            "{}:{}",
+            url.host_str().unwrap_or_default(),
+            url.port_or_known_default().unwrap_or_default()
+        );
+        //the slot is claimed before sleeping so concurrent requests line up behind each other
+        let slot: Instant = {
+            let mut next = self.next.lock().unwrap();
+            let now: Instant = Instant::now();
+            let slot: Instant = next.get(&host).map_or(now, |next| (*next).max(now));
+            next.insert(host, slot + self.interval);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
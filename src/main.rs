@@ -1,10 +1,43 @@
+#![allow(clippy::needless_return)]
+pub mod bench;
+mod buildings;
+pub mod cache;
+mod campus;
+pub mod cli;
+pub mod conflict;
+pub mod error;
 pub mod fetch;
+mod http;
+pub mod parallel;
+pub mod profile;
+pub mod rating;
+pub mod retry;
 pub mod schedule;
+pub mod search;
+pub mod source;
 pub mod structs;
+mod time;
+mod walk;
 pub mod web;
-use fetch::fetch_all_courses;
-use schedule::{get_potential_schedules, schedules_for_display, schedules_with_alternatives};
-use std::{collections::HashMap, fs::File, path::Path};
+use clap::Parser;
+use conflict::ConflictGraph;
+use cli::{DataArgs, Cli, Command, GenerateArgs, OutputFormat};
+use error::FetchError;
+use fetch::{FetchReport, Fetcher};
+use profile::{MissingCourses, Profile};
+use schedule::{build_display_schedules, schedules_as_text, schedules_for_display};
+use search::ScheduleSearch;
+use buildings::{
+    UnknownBuildings, reject_sections, unknown_buildings, unknown_report, untimed_report,
+    untimed_walks,
+};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    process,
+    sync::Arc,
+};
+use walk::WalkModel;
 use structs::*;
 use web::launch_webpage;
 
@@ -15,9 +48,7 @@ use web::launch_webpage;
 //NEXT UP
 //TODO: Beginning layout of display page (scroll list on left, schedule view on right)
 //TODO: Full on schedule display on right side
-//TODO: Save chache for changing sorting/times without re-fetching
 //TODO: Add moveable blockout times
-//TODO: Add blockout windows
 
 
 //EVENTUALLY FEATURES
@@ -36,63 +67,290 @@ use web::launch_webpage;
 
 #[tokio::main]
 async fn main() {
+    let cli: Cli = Cli::parse();
 
+    match cli.command {
+        Command::Generate(args) => {
+            let profile: Profile = resolve(&args);
+            let (inputs, _) = load_inputs(&profile, &args.data).await;
+            if args.stream {
+                stream_schedules(&inputs, &profile.constraints, args.format);
+                return;
+            }
+            let all_schedules: Vec<DisplaySchedule> =
+                build_display_schedules(&inputs, &profile.constraints, &args.options());
+            match args.format {
+                OutputFormat::Text => print!(
+                    "{}",
+                    schedules_as_text(&all_schedules, &profile.constraints.blockouts)
+                ),
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&all_schedules).expect("Json writing error")
+                ),
+            }
+        }
+        Command::Serve(args) => {
+            let profile: Profile = resolve(&args);
+            let (inputs, warnings) = load_inputs(&profile, &args.data).await;
+            //Launch webpage to show results
+            launch_webpage(inputs, profile.constraints, args.options(), warnings).await;
+        }
+        Command::Bench(args) => {
+            let profile: Profile = resolve(&args.generate);
+            let (inputs, _) = load_inputs(&profile, &args.generate.data).await;
+            bench::run_bench(&inputs, &profile.constraints, args.runs);
+        }
+        Command::AddBuilding(args) => {
+            match buildings::add_building(&args.buildings, &args.code, args.lat, args.long) {
+                Ok(true) => eprintln!("Added {} to {}", args.code, args.buildings.display()),
+                Ok(false) => eprintln!("Moved {} in {}", args.code, args.buildings.display()),
+                Err(e) => {
+                    eprintln!("Could not update {}: {}", args.buildings.display(), e);
+                    process::exit(1);
+                }
+            }
+        }
+        Command::Fetch(args) => {
+            let fetcher: Fetcher = open_fetcher(&args.data);
+            let report: FetchReport = fetcher
+                .fetch_all_courses(&args.courses, &args.semester)
+                .await;
+            require_cached(&report);
+            for problem in report.problems() {
+                eprintln!("{}", problem);
+            }
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report.courses).expect("Json writing error")
+            );
+        }
+    }
+}
 
+///Prints each schedule as soon as the search finds it, without alternates or rating order
+fn stream_schedules(inputs: &ScheduleInputs, constraints: &Constraints, format: OutputFormat) {
+    let graph: ConflictGraph = ConflictGraph::new(inputs, constraints);
+    for (i, schedule) in ScheduleSearch::new(&graph).enumerate() {
+        let display: DisplaySchedule = schedules_for_display(
+            vec![
+                graph
+                    .schedule(&schedule)
+                    .into_iter()
+                    .map(|s| (s, Vec::new()))
+                    .collect(),
+            ],
+            &constraints.soft,
+        )
+        .remove(0);
+        match format {
+            OutputFormat::Text => {
+                println!("Schedule {}:", i + 1);
+                for section in &display {
+                    println!(
+                        "{}-{}, {}",
+                        section.course,
+                        section.section,
+                        professor_names(&section.professors)
+                    );
+                }
+                println!();
+            }
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string(&display).expect("Json writing error")
+            ),
+        }
+    }
+}
 
+///Turns the command line arguments into a profile, exiting with the problems if it is invalid
+fn resolve(args: &GenerateArgs) -> Profile {
+    match args.resolve_profile() {
+        Ok(profile) => profile,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    }
+}
 
-    
+///Opens the course source and rating provider, exiting if either can't be read
+fn open_fetcher(data: &DataArgs) -> Fetcher {
+    match data.fetcher() {
+        Ok(fetcher) => fetcher,
+        Err(e) => {
+            eprintln!("Could not open course data: {}", e);
+            process::exit(2);
+        }
+    }
+}
 
+///Offline runs can't fetch what is missing from the cache, so they stop instead of going on without it
+fn require_cached(report: &FetchReport) {
+    let missing: Vec<&str> = report
+        .failed_courses
+        .iter()
+        .filter(|(_, e)| matches!(e, FetchError::NotCached(_)))
+        .map(|(course, _)| course.as_str())
+        .collect();
+    if !missing.is_empty() {
+        eprintln!(
+            "Not in the cache, run without --offline to fetch: {}",
+            missing.join(", ")
+        );
+        process::exit(2);
+    }
+}
 
+///Describes each required course that couldn't be fetched or has no sections
+fn missing_required(required: &[String], courses: &CourseMap, semester: &str) -> Vec<String> {
+    required
+        .iter()
+        .filter_map(|course| match courses.get(course) {
+            None => Some(format!("{} could not be fetched", course)),
+            Some(sections) if sections.is_empty() => {
+                Some(format!("{} has no sections in semester {}", course, semester))
+            }
+            Some(_) => None,
+        })
+        .collect()
+}
 
-    //INPUTS============================================================================================
-    //courses that must be in every schedule
-    let desired: Vec<String> = vec![
-        "PHYS260".to_string(),
-        "ENES200".to_string(),
-        "ENME272".to_string(),
-        "ENME201".to_string(),
-        "ENES102".to_string(),
-        "UMRO".to_string(),
-    ];
+///One line for each course with sections whose times haven't been announced
+fn tba_warnings<'a>(courses: impl Iterator<Item = (&'a String, &'a SectionMap)>) -> Vec<String> {
+    courses
+        .filter_map(|(course, sections)| {
+            let tba: Vec<&str> = sections
+                .values()
+                .filter(|s| s.has_tba())
+                .map(|s| s.section.as_str())
+                .collect();
+            match tba.is_empty() {
+                true => None,
+                false => Some(format!(
+                    "{} section(s) {} have times to be announced and aren't checked for conflicts",
+                    course,
+                    tba.join(", ")
+                )),
+            }
+        })
+        .collect()
+}
 
-    //alternates can replace preferred courses as long as they don't overlap with required ones
-    let alternates: Vec<String> = vec![
-        "FREN103".to_string(),
-        "COMM107".to_string(),
-        "SPAN204".to_string(),
-        "CHBE473".to_string(),
-        "INAG110".to_string(),
-        "COMM200".to_string(),
-    ];
+///Fetches the courses and loads the custom sections and buildings named by the profile.
+///Also gives back everything the student should be warned about, which has already been printed:
+///what couldn't be fetched, unknown buildings, TBA meetings and walks that can't be timed
+async fn load_inputs(profile: &Profile, data: &DataArgs) -> (ScheduleInputs, Vec<String>) {
+    let fetcher: Fetcher = open_fetcher(data);
+    let desired: &Vec<String> = &profile.courses;
+    let alternates: &Vec<String> = &profile.alternates;
 
-    let semester: String = String::from("202601");
-    //==================================================================================================
+    let requested: Vec<String> = [desired.clone(), alternates.clone()].concat();
+    let mut report: FetchReport = fetcher
+        .fetch_all_courses(&requested, &profile.semester)
+        .await;
+    let mut every_course: CourseMap = std::mem::take(&mut report.courses);
 
-    let every_course: Vec<String> =
-        [desired.clone(), alternates.clone()].concat();
-    let mut every_course: CourseMap = fetch_all_courses(&every_course, &semester).await;
-    
     //add custom sections
-    let path: &Path = Path::new("cache/custom.json");
-    let file: Result<File, std::io::Error> = File::open(path);
-    if file.is_err() {
-        println!("No custom sections file found, skipping ...");
-    } else {
-        let custom_sections: CourseMap =
-            serde_json::from_reader(file.unwrap()).unwrap_or_default();
-        if custom_sections.is_empty() {
-            println!("No custom sections to insert or error reading custom sections for semester");
+    match File::open(&profile.custom_sections) {
+        Err(_) => eprintln!("No custom sections file found, skipping ..."),
+        Ok(file) => {
+            let custom_sections: CourseMap = serde_json::from_reader(file).unwrap_or_default();
+            if custom_sections.is_empty() {
+                eprintln!(
+                    "No custom sections to insert or error reading custom sections for semester"
+                );
+            }
+            let custom_length = custom_sections.len();
+            for (id, section) in custom_sections {
+                //a custom course stands in for one that couldn't be fetched
+                report.failed_courses.remove(&id);
+                every_course.insert(id, section);
+            }
+            eprintln!("Inserted {} custom section(s)", custom_length);
         }
-        let custom_length = custom_sections.len();
-        for (id, section) in custom_sections {
-            every_course.insert(id, section);
+    }
+    require_cached(&report);
+    let mut warnings: Vec<String> = report.problems();
+    for warning in &warnings {
+        eprintln!("{}", warning);
+    }
+
+    //get building information
+    let buildings: BuildingMap = match buildings::read_buildings(&profile.buildings) {
+        Ok(buildings) => buildings,
+        Err(e) => {
+            eprintln!(
+                "Could not read building file {}: {}",
+                profile.buildings.display(),
+                e
+            );
+            process::exit(2);
+        }
+    };
+
+    //blockout locations have to be real buildings so walking to and from them can be checked
+    for blockout in &profile.constraints.blockouts {
+        if let Some(location) = &blockout.location
+            && !buildings.contains_key(location)
+        {
+            eprintln!(
+                "Blockout {} is in unknown building {}",
+                blockout.describe(),
+                location
+            );
+            process::exit(2);
+        }
+    }
+
+    //meetings in buildings without coordinates are handled the way the profile asks, and reported.
+    //Rejecting happens before the missing course check, which catches courses left without sections
+    let policy: UnknownBuildings = profile.constraints.unknown_buildings;
+    let requested_sections = every_course
+        .iter()
+        .filter(|(course, _)| requested.contains(course))
+        .map(|(_, sections)| sections);
+    let unknown: BTreeMap<String, Vec<String>> = unknown_buildings(requested_sections, &buildings);
+    for line in unknown_report(&unknown, policy) {
+        eprintln!("{}", line);
+        warnings.push(line);
+    }
+    if policy == UnknownBuildings::Reject {
+        reject_sections(&mut every_course, &unknown);
+    }
+
+    //a required course that is missing would otherwise just disappear from every schedule
+    let missing: Vec<String> = missing_required(desired, &every_course, &profile.semester);
+    match profile.missing_courses {
+        _ if missing.is_empty() => {}
+        MissingCourses::Abort => {
+            eprintln!("Required course(s) missing, no schedules were generated:");
+            for problem in &missing {
+                eprintln!("  - {}", problem);
+            }
+            eprintln!("Use --missing-courses warn or optional to go on without them");
+            process::exit(2);
+        }
+        MissingCourses::Warn => {
+            for problem in missing {
+                let warning: String =
+                    format!("WARNING: schedules leave out a required course, {}", problem);
+                eprintln!("{}", warning);
+                warnings.push(warning);
+            }
+        }
+        MissingCourses::Optional => {
+            for problem in missing {
+                eprintln!("Treating as optional: {}", problem);
+            }
         }
-        println!("Inserted {} custom section(s)", custom_length);
     }
 
+    //missing and empty courses are left out, a course without sections would rule out every schedule
     let desired_courses: CourseMap = every_course
         .iter()
-        .filter(|(k, _)| desired.contains(k))
+        .filter(|(k, v)| desired.contains(k) && !v.is_empty())
         .map(|(k, v)| (k.clone(), v.clone()))
         .collect();
 
@@ -102,25 +360,47 @@ async fn main() {
         .map(|(k, v)| (k.clone(), v.clone()))
         .collect();
 
-    //get building information
-    let path: &Path = Path::new("cache/buildings.json");
-    let file: File = File::open(path).expect("Failed to open buildings file");
-    let buildings: HashMap<String, BuildingData> =
-        serde_json::from_reader(file).expect("Json parsing error");
-
-    //generate all potential schedules
-    let potential_schedules: Vec<Schedule> = get_potential_schedules(desired_courses, &buildings);
-
-    //generate alternates for those schedules
-    let schedules_with_alternates: Vec<ScheduleWithAlternates> = schedules_with_alternatives(
-        potential_schedules,
-        &buildings,
-        &alternate_courses,
-    );
-
-    //format for display
-    let all_schedules: Vec<DisplaySchedule> = schedules_for_display(schedules_with_alternates);
-
-    //Launch webpage to show results
-    launch_webpage(all_schedules).await;
+    //sections still waiting on their times can't be checked, so whoever picks one should know
+    for warning in tba_warnings(desired_courses.iter().chain(&alternate_courses)) {
+        eprintln!("{}", warning);
+        warnings.push(warning);
+    }
+
+    let walk: Arc<dyn WalkModel> = match profile.walk_model.open(&buildings, &data.cache_dir) {
+        Ok(walk) => Arc::from(walk),
+        Err(e) => {
+            eprintln!("Could not open walk model: {}", e);
+            process::exit(2);
+        }
+    };
+
+    //walks between known buildings the model can't time get the same treatment as unknown buildings
+    let used_buildings = desired_courses
+        .values()
+        .chain(alternate_courses.values())
+        .flat_map(|sections| sections.values())
+        .flat_map(|section| section.classtimes.values().flatten())
+        .map(|meeting| meeting.building.as_str())
+        .chain(
+            profile
+                .constraints
+                .blockouts
+                .iter()
+                .filter_map(|blockout| blockout.location.as_deref()),
+        )
+        .filter(|code| buildings.contains_key(*code));
+    let untimed: Vec<(String, String)> =
+        untimed_walks(used_buildings, walk.as_ref(), profile.constraints.walk_speed);
+    for line in untimed_report(&untimed, policy) {
+        eprintln!("{}", line);
+        warnings.push(line);
+    }
+
+    let inputs: ScheduleInputs = ScheduleInputs {
+        desired_courses,
+        alternate_courses,
+        buildings,
+        walk,
+    };
+    (inputs, warnings)
 }
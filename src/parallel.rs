@@ -0,0 +1,150 @@
+use crate::conflict::{ConflictGraph, SectionIds};
+use crate::schedule::{rating, sort_rated, with_alternates};
+use crate::search::ScheduleSearch;
+use crate::structs::*;
+use std::sync::Mutex;
+use std::thread;
+
+///How many worker threads to use when none are asked for
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+///Runs `work` on every item across `threads` workers and gives the results back in item order.
+///Workers take the next item as soon as they finish one, so uneven items still spread out
+pub fn parallel_map<T, R, F>(items: Vec<T>, threads: usize, work: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let threads: usize = threads.clamp(1, items.len().max(1));
+    if threads == 1 {
+        return items.into_iter().map(work).collect();
+    }
+
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done: Vec<(usize, R)> = Vec::new();
+                    loop {
+                        let next: Option<(usize, T)> = queue.lock().unwrap().next();
+                        match next {
+                            Some((i, item)) => done.push((i, work(item))),
+                            None => return done,
+                        }
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    //put everything back in the order it was handed out
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+///Same schedules in the same order as get_potential_schedules, with each section of the
+///first course placed searched on its own thread
+pub fn parallel_potential_schedules(graph: &ConflictGraph, threads: usize) -> Vec<SectionIds> {
+    let subtrees: Vec<ScheduleSearch> = ScheduleSearch::new(graph).split();
+    parallel_map(subtrees, threads, |search| search.collect::<Vec<SectionIds>>())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+///Same result as schedules_with_alternatives, with alternates found on several threads
+pub fn parallel_schedules_with_alternatives(
+    graph: &ConflictGraph,
+    potential_schedules: Vec<SectionIds>,
+    constraints: &Constraints,
+    threads: usize,
+) -> Vec<ScheduleWithAlternates> {
+    //batches keep the per item locking small next to the work
+    let batches: Vec<Vec<SectionIds>> = potential_schedules
+        .chunks(256)
+        .map(|batch| batch.to_vec())
+        .collect();
+    let alternates: &[String] = graph.alternate_courses();
+    let rated: Vec<(f32, ScheduleWithAlternates)> = parallel_map(batches, threads, |batch| {
+        batch
+            .iter()
+            .map(|schedule| {
+                let single_with_alts: ScheduleWithAlternates = with_alternates(graph, schedule);
+                (
+                    rating(&single_with_alts, alternates, &constraints.soft),
+                    single_with_alts,
+                )
+            })
+            .collect::<Vec<(f32, ScheduleWithAlternates)>>()
+    })
+    .into_iter()
+    .flatten()
+    .collect();
+
+    sort_rated(rated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conflict::tests::{sample_constraints, sample_inputs};
+    use crate::schedule::{get_potential_schedules, schedules_with_alternatives};
+
+    fn codes(schedules: &[ScheduleWithAlternates]) -> Vec<Vec<(String, Vec<String>)>> {
+        schedules
+            .iter()
+            .map(|schedule| {
+                schedule
+                    .iter()
+                    .map(|(s, alts)| {
+                        let alts: Vec<String> = alts
+                            .iter()
+                            .map(|a| format!("{}-{}", a.course, a.section))
+                            .collect();
+                        (format!("{}-{}", s.course, s.section), alts)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn threads_give_the_serial_results_in_the_serial_order() {
+        let (inputs, constraints) = (sample_inputs(), sample_constraints());
+        let graph: ConflictGraph = ConflictGraph::new(&inputs, &constraints);
+        let serial: Vec<SectionIds> = get_potential_schedules(&graph);
+        let serial_alts: Vec<ScheduleWithAlternates> =
+            schedules_with_alternatives(&graph, serial.clone(), &constraints);
+        for threads in [1, 2, 4] {
+            let potential: Vec<SectionIds> = parallel_potential_schedules(&graph, threads);
+            assert_eq!(potential, serial, "{} threads", threads);
+            let with_alts: Vec<ScheduleWithAlternates> =
+                parallel_schedules_with_alternatives(&graph, potential, &constraints, threads);
+            assert_eq!(
+                codes(&with_alts),
+                codes(&serial_alts),
+                "{} threads",
+                threads
+            );
+        }
+    }
+
+    #[test]
+    fn parallel_map_keeps_item_order() {
+        let items: Vec<u64> = (0..1000).collect();
+        //uneven work, so later items often finish first
+        let work = |i: u64| (0..(i % 7) * 1000).fold(i, |acc, x| acc ^ x);
+        let serial: Vec<u64> = items.iter().copied().map(work).collect();
+        assert_eq!(parallel_map(items.clone(), 4, work), serial);
+        assert_eq!(parallel_map(items, 64, work), serial);
+        assert!(parallel_map(Vec::<u64>::new(), 4, work).is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::structs::Constraints;
+use crate::walk::WalkSpec;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::{fmt, fs, path::Path, path::PathBuf};
+
+///Everything about a student's request, enough to reproduce a run of the pipeline
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Profile {
+    //courses that must be in every schedule
+    pub courses: Vec<String>,
+    //alternates can replace preferred courses as long as they don't overlap with required ones
+    pub alternates: Vec<String>,
+    //what to do when a required course couldn't be fetched or has no sections
+    pub missing_courses: MissingCourses,
+    pub semester: String,
+    #[serde(flatten)]
+    pub constraints: Constraints,
+    pub custom_sections: PathBuf,
+    pub buildings: PathBuf,
+    //how walking times between buildings are worked out
+    pub walk_model: WalkSpec,
+    //settings the file has that a profile doesn't, usually misspelled ones. Kept so validate can
+    //report them instead of quietly using the defaults
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, serde_json::Value>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            courses: Vec::new(),
+            alternates: Vec::new(),
+            missing_courses: MissingCourses::default(),
+            semester: String::from("202601"),
+            constraints: Constraints::default(),
+            custom_sections: PathBuf::from("cache/custom.json"),
+            buildings: PathBuf::from("cache/buildings.json"),
+            walk_model: WalkSpec::default(),
+            unknown: BTreeMap::new(),
+        }
+    }
+}
+
+///What happens to a required course that couldn't be fetched or has no sections
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingCourses {
+    ///Stop before generating anything
+    #[default]
+    Abort,
+    ///Generate schedules without the course and warn loudly about it
+    Warn,
+    ///Generate schedules without the course, as if it had never been required
+    Optional,
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(PathBuf, std::io::Error),
+    Toml(PathBuf, String),
+    Json(PathBuf, serde_json::Error),
+    UnknownFormat(PathBuf),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io(path, e) => write!(f, "could not access {}: {}", path.display(), e),
+            ProfileError::Toml(path, e) => write!(f, "bad toml in {}: {}", path.display(), e),
+            ProfileError::Json(path, e) => write!(f, "bad json in {}: {}", path.display(), e),
+            ProfileError::UnknownFormat(path) => write!(
+                f,
+                "{} should end in .toml or .json so the format is known",
+                path.display()
+            ),
+            ProfileError::Invalid(problems) => {
+                write!(f, "invalid profile:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+enum Format {
+    Toml,
+    Json,
+}
+
+fn format_of(path: &Path) -> Result<Format, ProfileError> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => Ok(Format::Toml),
+        Some("json") => Ok(Format::Json),
+        _ => Err(ProfileError::UnknownFormat(path.to_path_buf())),
+    }
+}
+
+impl Profile {
+    ///Reads a profile from a .toml or .json file. Call validate once any overrides are applied
+    pub fn load(path: &Path) -> Result<Profile, ProfileError> {
+        let format: Format = format_of(path)?;
+        let raw: String =
+            fs::read_to_string(path).map_err(|e| ProfileError::Io(path.to_path_buf(), e))?;
+        let profile: Profile = match format {
+            Format::Toml => toml::from_str(&raw)
+                .map_err(|e| ProfileError::Toml(path.to_path_buf(), e.to_string()))?,
+            Format::Json => serde_json::from_str(&raw)
+                .map_err(|e| ProfileError::Json(path.to_path_buf(), e))?,
+        };
+        Ok(profile)
+    }
+
+    ///Writes the profile to a .toml or .json file
+    pub fn save(&self, path: &Path) -> Result<(), ProfileError> {
+        let raw: String = match format_of(path)? {
+            Format::Toml => toml::to_string_pretty(self)
+                .map_err(|e| ProfileError::Toml(path.to_path_buf(), e.to_string()))?,
+            Format::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| ProfileError::Json(path.to_path_buf(), e))?,
+        };
+        fs::write(path, raw).map_err(|e| ProfileError::Io(path.to_path_buf(), e))
+    }
+
+    ///Checks the profile for mistakes, reporting all of them at once
+    pub fn validate(&self) -> Result<(), ProfileError> {
+        let mut problems: Vec<String> = Vec::new();
+
+        for setting in self.unknown.keys() {
+            problems.push(format!("'{}' is not a profile setting", setting));
+        }
+        if self.courses.is_empty() {
+            problems.push(String::from("at least one required course is needed"));
+        }
+        for (i, course) in self.courses.iter().chain(&self.alternates).enumerate() {
+            if course.is_empty() || !course.chars().all(|c| c.is_ascii_alphanumeric()) {
+                problems.push(format!("'{}' is not a course code", course));
+            }
+            if self.courses.iter().chain(&self.alternates).take(i).any(|c| c == course) {
+                problems.push(format!("{} is listed more than once", course));
+            }
+        }
+
+        let month: u32 = self.semester.get(4..).and_then(|m| m.parse().ok()).unwrap_or(0);
+        if self.semester.len() != 6
+            || !self.semester.chars().all(|c| c.is_ascii_digit())
+            || !(1..=12).contains(&month)
+        {
+            problems.push(format!(
+                "semester '{}' should look like 202601 (year then month)",
+                self.semester
+            ));
+        }
+
+        problems.extend(self.constraints.problems());
+
+        if !self.buildings.is_file() {
+            problems.push(format!(
+                "buildings file {} does not exist",
+                self.buildings.display()
+            ));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ProfileError::Invalid(problems))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::TimeOfDay;
+
+    ///Writes `raw` to a file named `name` in a directory of its own and loads it
+    fn load(name: &str, raw: &str) -> Result<Profile, ProfileError> {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("profile-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path: PathBuf = dir.join(name);
+        fs::write(&path, raw).unwrap();
+        let profile: Result<Profile, ProfileError> = Profile::load(&path);
+        fs::remove_file(&path).unwrap();
+        profile
+    }
+
+    fn problems(profile: &Profile) -> Vec<String> {
+        match profile.validate() {
+            Ok(()) => Vec::new(),
+            Err(ProfileError::Invalid(problems)) => problems,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn example_profile_loads_and_is_valid() {
+        let profile: Profile = Profile::load(Path::new("profiles/engineering.toml")).unwrap();
+        assert_eq!(profile.courses[0], "PHYS260");
+        assert_eq!(profile.constraints, Constraints::default());
+        assert_eq!(problems(&profile), Vec::<String>::new());
+    }
+
+    #[test]
+    fn toml_and_json_read_the_same() {
+        let toml: Profile = load(
+            "same.toml",
+            "courses = [\"PHYS260\"]\nearliest = \"10:00\"\nwalk_speed = 1.2\n\n[days.F]\nlatest = \"15:00\"\n",
+        )
+        .unwrap();
+        let json: Profile = load(
+            "same.json",
+            r#"{"courses": ["PHYS260"], "earliest": 1000, "walk_speed": 1.2, "days": {"F": {"latest": "3:00pm"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(toml, json);
+        assert_eq!(toml.constraints.earliest, TimeOfDay::at(10, 0));
+        assert_eq!(
+            toml.constraints.days["F"].latest,
+            Some(TimeOfDay::at(15, 0))
+        );
+    }
+
+    #[test]
+    fn misspelled_settings_are_reported() {
+        let profile: Profile = load(
+            "misspelled.toml",
+            "courses = [\"PHYS260\"]\nearlist = 1000\nwalkspeed = 9\n",
+        )
+        .unwrap();
+        assert_eq!(profile.constraints, Constraints::default());
+        assert_eq!(
+            problems(&profile),
+            [
+                "'earlist' is not a profile setting",
+                "'walkspeed' is not a profile setting"
+            ]
+        );
+        //and they aren't written back out
+        assert!(!toml::to_string(&profile).unwrap().contains("earlist"));
+    }
+
+    #[test]
+    fn misspelled_nested_settings_are_errors() {
+        for raw in [
+            "[days.F]\nlatst = \"15:00\"\n",
+            "[[blockouts]]\ndays = \"MWF\"\nstart = 1200\nend = 1300\nlabl = \"Lunch\"\n",
+            "[[soft]]\nkind = \"day_off\"\nday = \"F\"\nweight = 3\nwieght = 4\n",
+        ] {
+            assert!(
+                matches!(load("nested.toml", raw), Err(ProfileError::Toml(..))),
+                "{}",
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        let profile: Profile = Profile {
+            courses: vec![
+                String::from("PHYS260"),
+                String::from("PHYS 260"),
+                String::from("PHYS260"),
+            ],
+            semester: String::from("2026"),
+            buildings: PathBuf::from("no/such/buildings.json"),
+            ..Profile::default()
+        };
+        assert_eq!(
+            problems(&profile),
+            [
+                "'PHYS 260' is not a course code",
+                "PHYS260 is listed more than once",
+                "semester '2026' should look like 202601 (year then month)",
+                "buildings file no/such/buildings.json does not exist",
+            ]
+        );
+        assert_eq!(
+            problems(&Profile::default()),
+            ["at least one required course is needed"]
+        );
+    }
+
+    #[test]
+    fn profiles_need_a_known_format() {
+        assert!(matches!(
+            load("profile.yaml", "courses: [PHYS260]"),
+            Err(ProfileError::UnknownFormat(_))
+        ));
+        assert!(matches!(load("bad.json", "{"), Err(ProfileError::Json(..))));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::error::FetchError;
+use crate::http::HttpClient;
+use crate::structs::ProfRatingInput;
+use futures::future::BoxFuture;
+use reqwest::{Response, StatusCode};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+///A professor's rating, None when the provider doesn't know them
+pub type RatingResult = Result<Option<f32>, FetchError>;
+
+///Somewhere a professor's rating can be looked up
+pub trait RatingProvider: Send + Sync {
+    ///The professor's rating, or None when it isn't known
+    fn rating<'a>(&'a self, professor: &'a str) -> BoxFuture<'a, RatingResult>;
+
+    ///Whether looked up ratings are worth keeping in the rating cache
+    fn cacheable(&self) -> bool {
+        true
+    }
+}
+
+pub const PLANETTERP_URL: &str = "https://planetterp.com";
+
+///Average ratings from planetterp.com, or anything serving the same paths at `base_url`
+pub struct Planetterp {
+    http: HttpClient,
+    base_url: String,
+}
+
+impl Planetterp {
+    pub fn new(base_url: &str, http: HttpClient) -> Planetterp {
+        Planetterp {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl RatingProvider for Planetterp {
+    ///Looks up a professor's average rating on planetterp
+    fn rating<'a>(&'a self, professor: &'a str) -> BoxFuture<'a, RatingResult> {
+        Box::pin(async move {
+            let (firstname, lastname) = professor.split_once(' ').unwrap_or_default();
+            let response: Response = self
+                .http
+                .get(&format!(
+                    "{}/api/v1/professor?name={}%20{}",
+                    self.base_url, firstname, lastname
+                ))
+                .await?;
+            //planetterp answers 404 for professors it has no page for
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            let raw: String = FetchError::check(response)?.text().await?;
+            let prof_data: ProfRatingInput =
+                FetchError::parse(&raw, &format!("planetterp rating of {}", professor))?;
+            Ok(prof_data.average_rating)
+        })
+    }
+}
+
+///A fixed list of ratings by professor name, from a json object or a `name,rating` csv file
+pub struct StaticRatings {
+    ratings: HashMap<String, f32>,
+}
+
+impl StaticRatings {
+    pub fn new(ratings: HashMap<String, f32>) -> StaticRatings {
+        StaticRatings { ratings }
+    }
+
+    pub fn from_file(path: &Path) -> Result<StaticRatings, Box<dyn std::error::Error>> {
+        let raw: String = fs::read_to_string(path)?;
+        let ratings: HashMap<String, f32> = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&raw)?,
+            Some("csv") => parse_csv(&raw)?,
+            _ => return Err(format!("{} is not a .json or .csv file", path.display()).into()),
+        };
+        Ok(StaticRatings::new(ratings))
+    }
+}
+
+///Reads `name,rating` lines. Blank lines and a header line are skipped
+fn parse_csv(raw: &str) -> Result<HashMap<String, f32>, String> {
+    let mut ratings: HashMap<String, f32> = HashMap::new();
+    for (i, line) in raw.lines().enumerate() {
+        let line: &str = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((name, rating)) = line.rsplit_once(',') else {
+            return Err(format!("line {} is not name,rating: {}", i + 1, line));
+        };
+        match rating.trim().parse::<f32>() {
+            Ok(rating) => {
+                ratings.insert(name.trim().trim_matches('"').to_string(), rating);
+            }
+            Err(_) if i == 0 => continue,
+            Err(_) => return Err(format!("line {} has a bad rating: {}", i + 1, line)),
+        }
+    }
+    Ok(ratings)
+}
+
+impl RatingProvider for StaticRatings {
+    fn rating<'a>(&'a self, professor: &'a str) -> BoxFuture<'a, RatingResult> {
+        Box::pin(async move { Ok(self.ratings.get(professor).copied()) })
+    }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
+}
+
+///No ratings at all, every professor is rated 0
+pub struct NoRatings;
+
+impl RatingProvider for NoRatings {
+    fn rating<'a>(&'a self, _professor: &'a str) -> BoxFuture<'a, RatingResult> {
+        Box::pin(async { Ok(None) })
+    }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
+}
+
+///Which rating provider a run uses, as given on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub enum RatingSpec {
+    Planetterp,
+    File(PathBuf),
+    None,
+}
+
+impl RatingSpec {
+    ///Parses `planetterp`, `file:PATH` or `none`
+    pub fn parse(s: &str) -> Result<RatingSpec, String> {
+        match s.split_once(':') {
+            None if s == "planetterp" => Ok(RatingSpec::Planetterp),
+            None if s == "none" => Ok(RatingSpec::None),
+            Some(("file", path)) if !path.is_empty() => Ok(RatingSpec::File(PathBuf::from(path))),
+            _ => Err(format!(
+                "unknown rating provider {}, expected planetterp, file:PATH or none",
+                s
+            )),
+        }
+    }
+
+    ///Opens the provider, with planetterp served from `planetterp_url` through the shared client
+    pub fn open(
+        &self,
+        planetterp_url: &str,
+        http: &HttpClient,
+    ) -> Result<Box<dyn RatingProvider>, Box<dyn std::error::Error>> {
+        Ok(match self {
+            RatingSpec::Planetterp => Box::new(Planetterp::new(planetterp_url, http.clone())),
+            RatingSpec::File(path) => Box::new(StaticRatings::from_file(path)?),
+            RatingSpec::None => Box::new(NoRatings),
+        })
+    }
+}
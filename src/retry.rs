@@ -0,0 +1,170 @@
+use crate::error::{FetchError, Retry};
+use std::future::Future;
+use std::time::Duration;
+
+//the longest a server asking us to slow down is waited for
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+///How often and how patiently failed requests are tried again.
+///Waits double after every attempt, up to `max_delay`, with jitter so concurrent
+///requests that failed together don't all come back at the same moment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    //tries after the first one
+    pub retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            ..RetryPolicy::default()
+        }
+    }
+
+    ///Wait before the given retry (0 for the first): half the backoff plus a random part of the other half
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff: Duration = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let half: Duration = backoff / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+
+    ///Runs `op` until it succeeds, fails with an error that won't go away, or runs out of retries.
+    ///Gives back the last error
+    pub async fn run<T, F, Fut>(&self, what: &str, mut op: F) -> Result<T, FetchError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, FetchError>>,
+    {
+        let mut retry: u32 = 0;
+        loop {
+            let error: FetchError = match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            let wait: Duration = match error.retry() {
+                _ if retry >= self.retries => return Err(error),
+                Retry::Never => return Err(error),
+                Retry::Backoff => self.delay(retry),
+                Retry::After(wait) => wait.min(MAX_RETRY_AFTER),
+            };
+            retry += 1;
+            eprintln!(
+                "Retrying {} in {}ms ({}/{}): {}",
+                what,
+                wait.as_millis(),
+                retry,
+                self.retries,
+                error
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+    use std::cell::Cell;
+
+    fn quick(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn delays_double_with_jitter_and_stay_under_the_cap() {
+        let policy: RetryPolicy = RetryPolicy::default();
+        for retry in 0..8 {
+            let backoff: Duration = (policy.base_delay * 2u32.pow(retry)).min(policy.max_delay);
+            for _ in 0..200 {
+                let delay: Duration = policy.delay(retry);
+                assert!(
+                    delay >= backoff / 2 && delay <= backoff,
+                    "{} {:?}",
+                    retry,
+                    delay
+                );
+            }
+        }
+        //far past the cap nothing overflows
+        for retry in [31, 32, 1000, u32::MAX] {
+            assert!(policy.delay(retry) <= policy.max_delay);
+            assert!(policy.delay(retry) >= policy.max_delay / 2);
+        }
+    }
+
+    #[test]
+    fn delays_are_jittered() {
+        let policy: RetryPolicy = RetryPolicy::default();
+        let delays: Vec<Duration> = (0..20).map(|_| policy.delay(2)).collect();
+        assert!(delays.iter().any(|&delay| delay != delays[0]));
+    }
+
+    #[tokio::test]
+    async fn retries_until_it_works() {
+        let calls: Cell<u32> = Cell::new(0);
+        let result: Result<u32, FetchError> = quick(3)
+            .run("test", || {
+                calls.set(calls.get() + 1);
+                let call: u32 = calls.get();
+                async move {
+                    match call {
+                        1 => Err(FetchError::RateLimited(Some(Duration::from_millis(1)))),
+                        2 => Err(FetchError::Status(StatusCode::SERVICE_UNAVAILABLE)),
+                        _ => Ok(call),
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_retries_with_the_last_error() {
+        let calls: Cell<u32> = Cell::new(0);
+        let result: Result<(), FetchError> = quick(2)
+            .run("test", || {
+                calls.set(calls.get() + 1);
+                async { Err(FetchError::Status(StatusCode::BAD_GATEWAY)) }
+            })
+            .await;
+        assert!(matches!(
+            result,
+            Err(FetchError::Status(StatusCode::BAD_GATEWAY))
+        ));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn errors_that_would_repeat_are_tried_once() {
+        let calls: Cell<u32> = Cell::new(0);
+        let result: Result<(), FetchError> = quick(3)
+            .run("test", || {
+                calls.set(calls.get() + 1);
+                async { Err(FetchError::Status(StatusCode::NOT_FOUND)) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -1,61 +1,40 @@
+use crate::buildings::UnknownBuildings;
+use crate::conflict::{ConflictGraph, SectionIds};
+use crate::parallel::{parallel_potential_schedules, parallel_schedules_with_alternatives};
+use crate::search::{ScheduleSearch, TopKSearch};
 use crate::structs::*;
-use haversine_rs::{distance, point::Point, units::Unit};
+use crate::time::{TimeOfDay, Weekday};
+use crate::walk::WalkModel;
 use std::collections::HashMap;
 
 //walk speed in meters per second
-const WALK_SPEED: f32 = 1.42;
+pub const WALK_SPEED: f32 = 1.42;
 //earlist and latest time to go to class
-const EARLIST: u32 = 900;
-const LATEST: u32 = 1700;
-
-///Turns computer formatted time into human formatted time
-pub fn un_military_time(time: u32) -> String {
-    let mut to_return: String;
-    if time >= 1300 {
-        to_return = (time - 1200).to_string() + "pm";
-    } else if time == 1200 {
-        to_return = time.to_string() + "pm";
-    } else {
-        to_return = time.to_string() + "am";
-    }
-
-    to_return.insert(to_return.len() - 4, ':');
-    to_return
-}
-/// Convert HHMM -> total minutes since midnight and compare
-pub fn time_between(first: u32, second: u32) -> u32 {
-    let to_minutes = |time: u32| -> u32 {
-        let hours = time / 100;
-        let minutes = time % 100;
-        hours * 60 + minutes
-    };
-
-    return to_minutes(second) - to_minutes(first);
-}
-
-/// takes two sections and determines if they have overlapping time slots, unwalkable, or too early or late
+pub const EARLIST: TimeOfDay = TimeOfDay::at(9, 0);
+pub const LATEST: TimeOfDay = TimeOfDay::at(17, 0);
+//minutes early a student should get to class
+pub const ARRIVAL_BUFFER: u32 = 5;
+//flip on to print why the debug pair below conflicts
+const DEBUG_CONFLICTS: bool = false;
+
+/// takes two sections and determines if they have overlapping time slots or are unwalkable.
+/// Too early or late sections are caught beforehand by Section::is_allowed
 pub fn is_conflict(
     section1: &Section,
     section2: &Section,
-    buildings: &HashMap<String, BuildingData>,
-    walk_speed: f32,
-    earliest: u32,
-    latest: u32,
+    walk: &dyn WalkModel,
+    constraints: &Constraints,
 ) -> bool {
+    let walk_speed: f32 = constraints.walk_speed;
+
     //TO DEBUG:
-    let tester: bool;
-    if (0 == 1)
+    let tester: bool = DEBUG_CONFLICTS
         && section1.course == "FREN103"
         && section1.section == "0301"
         && section2.course == "PHYS260"
-        && section2.section == "0201"
-    {
-        tester = true;
-    } else {
-        tester = false;
-    }
+        && section2.section == "0201";
 
-    for day in 1..6 {
+    for day in Weekday::ALL {
         //if the day is present in both sections
         if let (Some(times_from_1), Some(times_from_2)) =
             (section1.classtimes.get(&day), section2.classtimes.get(&day))
@@ -63,25 +42,6 @@ pub fn is_conflict(
             //compare every meeting on every day from both courses with each other
             for times1 in times_from_1 {
                 for times2 in times_from_2 {
-                    //if the class starts or ends to early, this section is deemed a conflict
-                    if times1.start < earliest
-                        || times2.start < earliest
-                        || times1.end > latest
-                        || times2.end > latest
-                    {
-                        if tester {
-                            println!(
-                                "On day {}, {}-{} OR {}-{} ends too late/starts too early",
-                                day,
-                                section1.course,
-                                section1.section,
-                                section2.course,
-                                section2.section
-                            );
-                        }
-                        return true;
-                    }
-
                     //if any start or end times are shared, they overlap
                     if times1.start == times2.start
                         || times1.start == times2.end
@@ -103,7 +63,7 @@ pub fn is_conflict(
 
                     //order them by which one starts first
                     let mut chronological: [&StartEnd; 2] = [times1, times2];
-                    chronological.sort_by(|a, b| a.start.cmp(&b.start));
+                    chronological.sort_by_key(|a| a.start);
 
                     let first: &StartEnd = chronological[0];
                     let second: &StartEnd = chronological[1];
@@ -121,33 +81,26 @@ pub fn is_conflict(
                             );
                         }
                         return true;
-                    } else {
-                        //test to see if there is enough time to walk
-                        let time_between: u32 = time_between(first.end, second.start) * 60; //time between classes in seconds
-
-                        let pos1: Point = Point::new(
-                            buildings[&first.building].lat as f64,
-                            buildings[&first.building].long as f64,
-                        );
-                        let pos2: Point = Point::new(
-                            buildings[&second.building].lat as f64,
-                            buildings[&first.building].long as f64,
-                        );
-                        let pos3: Point = Point::new(
-                            buildings[&second.building].lat as f64,
-                            buildings[&second.building].long as f64,
-                        );
-
-                        //computes the maximum distance: we cannot take a straghit line, and must go straight East or West, then straight north or south
-                        //this simulates real walking where we often have to follow horizontal and vertical roads and paths
-                        let max_distance: f32 = distance(pos1, pos2, Unit::Meters) as f32
-                            + distance(pos2, pos3, Unit::Meters) as f32;
-                        //println!("Distance between {} and {} = {}", first.building, second.building, max_distance);
+                    } else if !first.building.is_empty() && !second.building.is_empty() {
+                        //test to see if there is enough time to walk (blockouts without a location are skipped)
+                        let time_between: u32 = first.end.minutes_until(second.start) * 60; //time between classes in seconds
+
+                        //walks the model can't time are left to the unknown building policy,
+                        //staying in the same building is no walk even when the building is unknown
+                        let policy: UnknownBuildings = constraints.unknown_buildings;
+                        let walk_time: f32 = match first.building == second.building {
+                            true => 0.0,
+                            false => {
+                                match walk.seconds(&first.building, &second.building, walk_speed) {
+                                    Some(walk_time) => walk_time,
+                                    None if policy == UnknownBuildings::Reject => return true,
+                                    None => policy.walk_distance() / walk_speed,
+                                }
+                            }
+                        };
                         /*
                         if tester && time_between == 600 {
                             println!("-----------------------------------");
-                            println!("Distance between {} and {} = {}", first.building, second.building, max_distance);
-                            let walk_time = max_distance / walk_speed;
                             println!("Walk time in minutes: {}", walk_time/60.);
 
                             //println!("How early in minutes: {}", (time_between as f32 /60.) -(walk_time/60.))
@@ -156,8 +109,8 @@ pub fn is_conflict(
                         }
                         */
 
-                        if time_between as f32 - (max_distance / walk_speed) < 300. {
-                            //if we can't get there 5 minutes early, deem this section as a conflict
+                        if time_between as f32 - walk_time < constraints.arrival_buffer_seconds() {
+                            //if we can't get there early enough, deem this section as a conflict
                             if tester {
                                 println!(
                                     "On day {}, {}-{} conflicts with {}-{}: Cant get there in time",
@@ -180,18 +133,76 @@ pub fn is_conflict(
 }
 
 ///gives a rating of the inputted schedule for ordering
-pub fn rating(schedule: &ScheduleWithAlternates, all_alternates: &Vec<String>) -> f32 {
-    //Sum of all professor ratings
-    let prof_rating: f32 = schedule.iter().map(|(s, _)| s.professor.rating).sum();
+pub fn rating(
+    schedule: &ScheduleWithAlternates,
+    all_alternates: &[String],
+    soft: &[SoftConstraint],
+) -> f32 {
+    let slots: Vec<(&Section, Vec<&Section>)> = schedule
+        .iter()
+        .map(|(s, alts)| (s, alts.iter().collect()))
+        .collect();
+
+    //soft constraints the schedule goes against
+    let sections: Vec<&Section> = schedule.iter().map(|(s, _)| s).collect();
+    let penalty: f32 = schedule
+        .iter()
+        .flat_map(|(s, _)| s.penalties(soft))
+        .chain(
+            day_off_penalties(&sections, soft)
+                .into_iter()
+                .map(|(_, p)| p),
+        )
+        .map(|p| p.amount)
+        .sum();
+
+    return unpenalized_rating(&slots, all_alternates) - penalty;
+}
+
+///Every preferred day off the schedule has classes on, charged once per day however many
+///sections meet then. Each comes with the index of the first section meeting that day
+pub fn day_off_penalties(sections: &[&Section], soft: &[SoftConstraint]) -> Vec<(usize, Penalty)> {
+    let mut penalties: Vec<(usize, Penalty)> = Vec::new();
+    for constraint in soft {
+        let SoftConstraint::DayOff { day, weight } = constraint else {
+            continue;
+        };
+        let Some(weekday) = Weekday::from_short_name(day) else {
+            continue;
+        };
+        let meeting: Vec<usize> = (0..sections.len())
+            .filter(|&i| sections[i].classtimes.contains_key(&weekday))
+            .collect();
+        if let Some(&first) = meeting.first() {
+            let names: Vec<String> = meeting
+                .iter()
+                .map(|&i| format!("{}-{}", sections[i].course, sections[i].section))
+                .collect();
+            penalties.push((
+                first,
+                Penalty {
+                    reason: format!("no day off on {} ({})", day, names.join(", ")),
+                    amount: *weight,
+                },
+            ));
+        }
+    }
+    penalties
+}
+
+///The rating of a schedule before soft constraint penalties, taking each section with its alternates
+pub fn unpenalized_rating(slots: &[(&Section, Vec<&Section>)], all_alternates: &[String]) -> f32 {
+    //Sum of all professor ratings, co-taught sections counting the average of their professors
+    let prof_rating: f32 = slots.iter().map(|(s, _)| s.professor_rating()).sum();
 
     //list of the average ratings for each alternate
-    let alt_ratings: Vec<f32> = schedule
+    let alt_ratings: Vec<f32> = slots
         .iter()
         .map(|(_, a)| {
             if a.is_empty() {
                 0.0
             } else {
-                let sum: f32 = a.iter().map(|s| s.professor.rating).sum();
+                let sum: f32 = a.iter().map(|s| s.professor_rating()).sum();
                 sum / a.len() as f32
             }
         })
@@ -202,22 +213,24 @@ pub fn rating(schedule: &ScheduleWithAlternates, all_alternates: &Vec<String>) -
 
     //rewards a schedule for giving freedom in which alternate courses are availible and when they can be taken
     let mut alternate_diversity_rating: f32 = 0.;
-    for (_, alts) in schedule {
+    for (_, alts) in slots {
         //for each course in the schedule
-        let course_alts: Vec<String> = alts.iter().map(|s| s.course.clone()).collect();
-        let mut counts: HashMap<String, usize> = HashMap::new(); //Amount of times each alternate course shows up
-        for alt in course_alts {
-            *counts.entry(alt).or_insert(0) += 1;
+        let mut counts: HashMap<&str, usize> = HashMap::new(); //Amount of times each alternate course shows up
+        for alt in alts {
+            *counts.entry(alt.course.as_str()).or_insert(0) += 1;
         }
         //get reward based on how many alternate courses (not sections) are availible for this course
         alternate_diversity_rating += counts.len() as f32;
         //get a reward based on the median number of sections per alternate course (rewards diverse options without overvaluing outliers)
-        for given_alternate in all_alternates.clone() {
+        for given_alternate in all_alternates {
             //insert  zeroes for sections not included
-            counts.entry(given_alternate).or_insert(0);
+            counts.entry(given_alternate.as_str()).or_insert(0);
         }
         let section_nums: Vec<f32> = counts.values().copied().map(|v| v as f32).collect();
-        alternate_diversity_rating += median(&section_nums);
+        //with no alternates at all there is nothing to reward
+        if !section_nums.is_empty() {
+            alternate_diversity_rating += median(&section_nums);
+        }
     }
     //temporarily display all three ratings for debugging
     /* 
@@ -230,7 +243,7 @@ pub fn rating(schedule: &ScheduleWithAlternates, all_alternates: &Vec<String>) -
 }
 
 ///Formats alternates to be nice on the eyes
-pub fn format_alternates(sections: &Vec<Section>, threshold: usize) -> String {
+pub fn format_alternates(sections: &[Section], threshold: usize) -> String {
     //count occurrences
     let mut counts: HashMap<&String, usize> = HashMap::new();
     for s in sections {
@@ -261,8 +274,8 @@ pub fn format_alternates(sections: &Vec<Section>, threshold: usize) -> String {
 }
 
 ///compute median of a collection of floats
-fn median(numbers: &Vec<f32>) -> f32 {
-    let mut numbers = numbers.clone();
+fn median(numbers: &[f32]) -> f32 {
+    let mut numbers = numbers.to_vec();
     numbers.sort_by(|a, b| a.partial_cmp(b).unwrap());
     let len = numbers.len();
 
@@ -278,159 +291,88 @@ fn median(numbers: &Vec<f32>) -> f32 {
     }
 }
 
-///converts the given convential time in string form into military time in integer form
-pub fn to_military(mut time: String) -> u32 {
-    let time2: String = time.clone();
-    time.retain(|c: char| c.is_numeric());
-    let nums: u32 = time.parse().unwrap_or_default();
-    if time2.contains("p") {
-        if nums >= 1200 && nums <= 1259 {
-            return nums;
-        } else {
-            return nums + 1200;
-        }
-    } else if time2.contains("a") {
-        return nums;
-    } else {
-        //happens when the time is unknown
-        //atleast I think so?
-        return 0;
-    }
-}
-
-///converts the given days in string form into a vector of numbers representing those days
-pub fn get_days(input: String) -> Vec<u32> {
-    let mut output: Vec<u32> = Vec::new();
-    let mut buffer: String = String::new();
-    for char in input.chars() {
-        if char == 'M' {
-            output.push(1);
-        } else if char == 'W' {
-            output.push(3);
-        } else if char == 'F' {
-            output.push(5);
-        } else {
-            //if it is one of the two letter combinations, use the buffer to aid in recognition
-            buffer.push(char);
-            if buffer == String::from("Tu") {
-                output.push(2);
-                buffer.clear();
-            } else if buffer == String::from("Th") {
-                output.push(4);
-                buffer.clear();
-            }
-        }
-    }
-
-    output
-}
-
 ///Generates all potential schedules from the desired courses
-pub fn get_potential_schedules(
-    desired_courses: CourseMap,
-    buildings: &BuildingMap,
-) -> Vec<Schedule> {
-    // Convert to a Vec so we can index only the first course.
-    let mut desired_courses: Vec<_> = desired_courses.into_iter().collect();
-    desired_courses.sort_by(|a, b| a.0.cmp(&b.0));
-    //sorting makes the process deterministic for testing
-
-
-    let mut potential_schedules: Vec<Schedule> = Vec::new();
-    //initialize will all sections of the first course
-    for section in desired_courses[0].1.values() {
-        potential_schedules.push(vec![section.clone()]);
-    }
-
-    for (_, sections) in desired_courses.iter().skip(1) {
-        let mut new_potential_schedules: Vec<Schedule> = Vec::new();
-        for (_, new_section) in sections {
-            'schedule_loop: for schedule in potential_schedules.clone() {
-                for section in schedule.clone() {
-                    if is_conflict(
-                        &section,
-                        &new_section,
-                        &buildings,
-                        WALK_SPEED,
-                        EARLIST,
-                        LATEST,
-                    ) {
-                        continue 'schedule_loop;
-                    }
-                }
-                //if we reach here, every section in the currently selected schedule is compatible with the new section
-                //this means that this schedule is valid, as it can hold 1 of every course we have iterated through at this point in time
-                let mut new_schedule: Vec<Section> = schedule.clone();
-                new_schedule.push(new_section.clone());
-                //sort courses alphabetically within their schedules
-                new_schedule.sort_by(|a, b| a.course.cmp(&b.course));
-                new_potential_schedules.push(new_schedule);
-            }
-        }
-        potential_schedules = new_potential_schedules;
-    }
-
-    potential_schedules
+pub fn get_potential_schedules(graph: &ConflictGraph) -> Vec<SectionIds> {
+    ScheduleSearch::new(graph).collect()
 }
 
 ///Computes possible alternates for all the given potential schedules
 pub fn schedules_with_alternatives(
-    potential_schedules: Vec<Schedule>,
-    buildings: &BuildingMap,
-    alternates: &CourseMap,
+    graph: &ConflictGraph,
+    potential_schedules: Vec<SectionIds>,
+    constraints: &Constraints,
 ) -> Vec<ScheduleWithAlternates> {
-    let mut schedules_with_alternates: Vec<ScheduleWithAlternates> = Vec::new();
-    for schedule in potential_schedules {
-        //generate the schedule with all possible alternates
-        let mut single_with_alts: Vec<(Section, Vec<Section>)> = schedule
-            .iter()
-            .map(|s| {
-                (
-                    s.clone(),
-                    s.find_alt(
-                        schedule.clone(),
-                        buildings,
-                        WALK_SPEED,
-                        EARLIST,
-                        LATEST,
-                        alternates,
-                    ),
-                )
-            })
-            .collect();
+    let alternates: &[String] = graph.alternate_courses();
+    let rated: Vec<(f32, ScheduleWithAlternates)> = potential_schedules
+        .iter()
+        .map(|schedule| {
+            let single_with_alts: ScheduleWithAlternates = with_alternates(graph, schedule);
+            (
+                rating(&single_with_alts, alternates, &constraints.soft),
+                single_with_alts,
+            )
+        })
+        .collect();
 
-        //sort alphabetically by course
-        single_with_alts.sort_by(|a, b| a.0.course.cmp(&b.0.course));
+    sort_rated(rated)
+}
 
-        schedules_with_alternates.push(single_with_alts);
-    }
+///Pairs each section of the schedule with every alternate that could replace it
+pub fn with_alternates(graph: &ConflictGraph, schedule: &[usize]) -> ScheduleWithAlternates {
+    //generate the schedule with all possible alternates
+    let mut single_with_alts: ScheduleWithAlternates = schedule
+        .iter()
+        .map(|&id| {
+            (
+                graph.section(id).clone(),
+                graph.schedule(&graph.find_alt(schedule, id)),
+            )
+        })
+        .collect();
+
+    //sort alphabetically by course
+    single_with_alts.sort_by(|a, b| a.0.course.cmp(&b.0.course));
+    single_with_alts
+}
 
-    //sort by rating, highest to lowest
-    let alternates: Vec<String> = alternates.keys().cloned().collect();
-    schedules_with_alternates.sort_by(|a, b| {
-        rating(b, &alternates)
-            .partial_cmp(&rating(a, &alternates))
-            .unwrap_or(std::cmp::Ordering::Equal)
+///Sorts schedules by their already computed rating, highest to lowest.
+///Equally rated schedules are ordered by their course and section codes, so the order
+///never depends on how the schedules were generated
+pub fn sort_rated(mut rated: Vec<(f32, ScheduleWithAlternates)>) -> Vec<ScheduleWithAlternates> {
+    rated.sort_by(|a, b| {
+        b.0.total_cmp(&a.0)
+            .then_with(|| section_codes(&a.1).cmp(section_codes(&b.1)))
     });
+    rated.into_iter().map(|(_, s)| s).collect()
+}
 
-    schedules_with_alternates
+///Course and section code of each section, in course order
+fn section_codes(schedule: &ScheduleWithAlternates) -> impl Iterator<Item = (&str, &str)> {
+    schedule
+        .iter()
+        .map(|(s, _)| (s.course.as_str(), s.section.as_str()))
 }
 
 ///Formats schedules with alternates for display
 pub fn schedules_for_display(
     schedules_with_alternates: Vec<ScheduleWithAlternates>,
+    soft: &[SoftConstraint],
 ) -> Vec<DisplaySchedule> {
     let mut all_schedules: Vec<DisplaySchedule> = Vec::new();
     for schedule in schedules_with_alternates {
+        let sections: Vec<&Section> = schedule.iter().map(|(s, _)| s).collect();
+        let day_offs: Vec<(usize, Penalty)> = day_off_penalties(&sections, soft);
         all_schedules.push(
             schedule
                 .iter()
-                .map(|(s, a)| DisplaySection {
-                    professor: s.professor.clone(),
+                .enumerate()
+                .map(|(i, (s, a))| DisplaySection {
+                    professors: s.professors.clone(),
+                    professor_rating: s.professor_rating(),
                     classtimes: s.humanize_times(),
                     course: s.course.clone(),
                     section: s.section.clone(),
-                    seats: s.seats.clone(),
+                    seats: s.seats,
                     alternates: {
                         //consolidate excess alternates and format for display
                         if a.is_empty() {
@@ -439,9 +381,262 @@ pub fn schedules_for_display(
                             format_alternates(a, 4)
                         }
                     },
+                    //a lost day off is listed once, under the first section meeting that day
+                    penalties: {
+                        let mut penalties: Vec<Penalty> = s.penalties(soft);
+                        for (first, penalty) in &day_offs {
+                            if *first == i {
+                                penalties.push(penalty.clone());
+                            }
+                        }
+                        penalties
+                    },
                 })
                 .collect(),
         );
     }
     all_schedules
 }
+
+///Runs generation, alternates and display formatting for the given inputs and constraints.
+///With `top`, only that many of the highest rated schedules are kept
+pub fn build_display_schedules(
+    inputs: &ScheduleInputs,
+    constraints: &Constraints,
+    options: &GenerateOptions,
+) -> Vec<DisplaySchedule> {
+    //conflict check every pair of sections once
+    let graph: ConflictGraph = ConflictGraph::new(inputs, constraints);
+
+    //generate all potential schedules, or only the best few when asked
+    let potential_schedules: Vec<SectionIds> = match options.top {
+        Some(k) => TopKSearch::new(&graph, constraints, k).run(),
+        None if options.threads > 1 => parallel_potential_schedules(&graph, options.threads),
+        None => get_potential_schedules(&graph),
+    };
+
+    //generate alternates for those schedules
+    let schedules_with_alternates: Vec<ScheduleWithAlternates> = if options.threads > 1 {
+        parallel_schedules_with_alternatives(
+            &graph,
+            potential_schedules,
+            constraints,
+            options.threads,
+        )
+    } else {
+        schedules_with_alternatives(&graph, potential_schedules, constraints)
+    };
+
+    //format for display
+    schedules_for_display(schedules_with_alternates, &constraints.soft)
+}
+
+///Formats display schedules as plain text, in the same layout as viable.txt
+pub fn schedules_as_text(all_schedules: &[DisplaySchedule], blockouts: &[Blockout]) -> String {
+    let mut output: String = String::from("Viable Schedules\n");
+    if !blockouts.is_empty() {
+        output += "\nBlocked out:\n";
+        for blockout in blockouts {
+            output += &format!("{}\n", blockout.describe());
+        }
+    }
+    for (i, schedule) in all_schedules.iter().enumerate() {
+        output += &format!("\nSchedule {}:\n", i + 1);
+        for section in schedule {
+            output += &format!(
+                "{}-{}, {}\n",
+                section.course,
+                section.section,
+                professor_names(&section.professors)
+            );
+        }
+        for penalty in schedule.iter().flat_map(|s| &s.penalties) {
+            output += &format!("  -{}: {}\n", penalty.amount, penalty.reason);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::walk::Manhattan;
+    use std::fs;
+
+    fn buildings() -> BuildingMap {
+        let raw: String = fs::read_to_string("cache/buildings.json").unwrap();
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    ///A section meeting (days, start, end, building) every week
+    fn section(course: &str, meetings: &[(&str, &str, &str, &str)]) -> Section {
+        let mut classtimes: Classtimes = Classtimes::new();
+        for &(days, start, end, building) in meetings {
+            for day in Weekday::parse_days(days).unwrap() {
+                classtimes.entry(day).or_default().push(StartEnd {
+                    building: building.to_string(),
+                    start: start.parse().unwrap(),
+                    end: end.parse().unwrap(),
+                });
+            }
+        }
+        Section {
+            professors: Vec::new(),
+            classtimes,
+            unscheduled: Vec::new(),
+            course: course.to_string(),
+            section: String::from("0101"),
+            seats: [30, 10, 0],
+        }
+    }
+
+    #[test]
+    fn back_to_back_in_one_unknown_building_is_no_walk() {
+        let walk: Manhattan = Manhattan::new(buildings());
+        let first: Section = section("AAA100", &[("M", "10:00", "10:50", "XYZ")]);
+        let second: Section = section("BBB100", &[("M", "11:00", "11:50", "XYZ")]);
+        let elsewhere: Section = section("CCC100", &[("M", "11:00", "11:50", "ESJ")]);
+        for policy in [
+            UnknownBuildings::WorstCase,
+            UnknownBuildings::NoWalk,
+            UnknownBuildings::Reject,
+        ] {
+            let constraints: Constraints = Constraints {
+                unknown_buildings: policy,
+                ..Constraints::default()
+            };
+            let conflict = |a: &Section, b: &Section| is_conflict(a, b, &walk, &constraints);
+            assert!(!conflict(&first, &second), "{:?}", policy);
+            assert!(!conflict(&second, &first), "{:?}", policy);
+            assert_eq!(
+                conflict(&first, &elsewhere),
+                policy != UnknownBuildings::NoWalk,
+                "{:?}",
+                policy
+            );
+        }
+    }
+
+    fn window(days: &str, start: &str, end: &str, weight: f32) -> SoftConstraint {
+        SoftConstraint::Window {
+            days: days.to_string(),
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+            weight,
+            label: None,
+        }
+    }
+
+    fn amounts(penalties: &[Penalty]) -> Vec<f32> {
+        penalties.iter().map(|p| p.amount).collect()
+    }
+
+    #[test]
+    fn windows_cost_each_meeting_inside_them() {
+        let section: Section = section(
+            "AAA100",
+            &[
+                ("MWF", "11:00", "11:50", "ESJ"),
+                ("Tu", "14:00", "15:15", "PHY"),
+            ],
+        );
+        let lunch: SoftConstraint = window("MTuWThF", "11:30", "13:00", 2.0);
+        let penalties: Vec<Penalty> = section.penalties(&[lunch]);
+        assert_eq!(amounts(&penalties), [2.0, 2.0, 2.0]);
+        assert_eq!(
+            penalties[0].reason,
+            "AAA100-0101 meets during 11:30am-1:00pm on M"
+        );
+        //touching the window isn't meeting during it
+        assert!(
+            section
+                .penalties(&[window("MWF", "11:50", "13:00", 2.0)])
+                .is_empty()
+        );
+        assert!(
+            section
+                .penalties(&[window("Th", "8:00", "18:00", 2.0)])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn avoided_buildings_cost_each_meeting_in_them() {
+        let section: Section = section(
+            "AAA100",
+            &[
+                ("MW", "11:00", "11:50", "ESJ"),
+                ("F", "11:00", "11:50", "PHY"),
+            ],
+        );
+        let avoid = |building: &str| SoftConstraint::AvoidBuilding {
+            building: building.to_string(),
+            weight: 1.5,
+        };
+        let penalties: Vec<Penalty> = section.penalties(&[avoid("ESJ")]);
+        assert_eq!(amounts(&penalties), [1.5, 1.5]);
+        assert_eq!(penalties[1].reason, "AAA100-0101 meets in ESJ on W");
+        assert!(section.penalties(&[avoid("EGR")]).is_empty());
+    }
+
+    #[test]
+    fn lost_day_off_is_charged_once_per_schedule() {
+        let sections: [Section; 3] = [
+            section("AAA100", &[("MW", "10:00", "10:50", "ESJ")]),
+            section("BBB100", &[("MWF", "11:00", "11:50", "ESJ")]),
+            section("CCC100", &[("TuF", "13:00", "14:15", "PHY")]),
+        ];
+        let day_off = |day: &str| SoftConstraint::DayOff {
+            day: day.to_string(),
+            weight: 3.0,
+        };
+        let soft: [SoftConstraint; 3] = [day_off("F"), day_off("Th"), day_off("Xy")];
+        let refs: Vec<&Section> = sections.iter().collect();
+
+        //sections never charge for it on their own
+        assert!(refs.iter().all(|s| s.penalties(&soft).is_empty()));
+        let penalties: Vec<(usize, Penalty)> = day_off_penalties(&refs, &soft);
+        assert_eq!(
+            penalties,
+            [(
+                1,
+                Penalty {
+                    reason: String::from("no day off on F (BBB100-0101, CCC100-0101)"),
+                    amount: 3.0,
+                }
+            )]
+        );
+
+        let schedule: ScheduleWithAlternates =
+            sections.iter().map(|s| (s.clone(), Vec::new())).collect();
+        let lost: f32 = rating(&schedule, &[], &[]) - rating(&schedule, &[], &soft);
+        assert_eq!(lost, 3.0);
+    }
+
+    #[test]
+    fn penalties_add_up_in_the_rating() {
+        let schedule: ScheduleWithAlternates = vec![
+            (
+                section("AAA100", &[("MW", "12:00", "12:50", "ESJ")]),
+                Vec::new(),
+            ),
+            (
+                section("BBB100", &[("F", "9:00", "9:50", "PHY")]),
+                Vec::new(),
+            ),
+        ];
+        let soft: [SoftConstraint; 3] = [
+            window("MW", "12:00", "13:00", 1.0),
+            SoftConstraint::DayOff {
+                day: String::from("F"),
+                weight: 3.0,
+            },
+            SoftConstraint::AvoidBuilding {
+                building: String::from("PHY"),
+                weight: 0.5,
+            },
+        ];
+        let lost: f32 = rating(&schedule, &[], &[]) - rating(&schedule, &[], &soft);
+        assert_eq!(lost, 1.0 + 1.0 + 3.0 + 0.5);
+    }
+}
@@ -0,0 +1,555 @@
+use crate::conflict::{BitSet, ConflictGraph, SectionIds};
+use crate::schedule::{day_off_penalties, unpenalized_rating};
+use crate::structs::{Constraints, Section, SoftConstraint};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+///The sections of each desired course, in the order the searches place courses:
+///most constrained first (the sort is stable, so ties stay alphabetical)
+fn course_levels(graph: &ConflictGraph) -> Vec<&[usize]> {
+    let mut levels: Vec<&[usize]> = graph
+        .desired_courses()
+        .iter()
+        .map(|course| graph.course_sections(course))
+        .collect();
+    levels.sort_by_key(|ids| ids.len());
+    levels
+}
+
+///Depth-first search over the desired courses that yields each valid schedule as it is found.
+///Courses with the fewest usable sections are placed first so dead ends are found early, and
+///a branch is dropped as soon as some unplaced course has no section left that fits
+pub struct ScheduleSearch<'a> {
+    graph: &'a ConflictGraph,
+    //section ids for each course, in the order courses are placed
+    levels: Vec<&'a [usize]>,
+    //the same sections as a set per level, for forward checking
+    level_sets: Vec<BitSet>,
+    //sections picked so far, one per placed course
+    chosen: Vec<usize>,
+    //blocked[i] is everything that conflicts with the first i picks
+    blocked: Vec<BitSet>,
+    //for each level up to the current one, the next candidate to try
+    next_try: Vec<usize>,
+}
+
+impl<'a> ScheduleSearch<'a> {
+    pub fn new(graph: &'a ConflictGraph) -> ScheduleSearch<'a> {
+        let levels: Vec<&[usize]> = course_levels(graph);
+
+        let level_sets: Vec<BitSet> = levels
+            .iter()
+            .map(|ids| {
+                let mut set: BitSet = BitSet::new(graph.len());
+                for &id in ids.iter() {
+                    set.insert(id);
+                }
+                set
+            })
+            .collect();
+
+        ScheduleSearch {
+            graph,
+            //nothing to search when no courses were asked for
+            next_try: if levels.is_empty() { Vec::new() } else { vec![0] },
+            levels,
+            level_sets,
+            chosen: Vec::new(),
+            blocked: vec![BitSet::new(graph.len())],
+        }
+    }
+}
+
+impl<'a> ScheduleSearch<'a> {
+    ///Splits the search into one search per section of the first course placed. Running them
+    ///in order gives the same schedules in the same order as running this search
+    pub fn split(self) -> Vec<ScheduleSearch<'a>> {
+        let Some(&first_level) = self.levels.first() else {
+            return Vec::new();
+        };
+        (0..first_level.len())
+            .map(|i| {
+                let mut levels: Vec<&'a [usize]> = self.levels.clone();
+                levels[0] = &first_level[i..i + 1];
+                let mut level_sets: Vec<BitSet> = self.level_sets.clone();
+                level_sets[0] = BitSet::new(self.graph.len());
+                level_sets[0].insert(first_level[i]);
+                ScheduleSearch {
+                    graph: self.graph,
+                    levels,
+                    level_sets,
+                    chosen: Vec::new(),
+                    blocked: vec![BitSet::new(self.graph.len())],
+                    next_try: vec![0],
+                }
+            })
+            .collect()
+    }
+}
+
+impl Iterator for ScheduleSearch<'_> {
+    type Item = SectionIds;
+
+    fn next(&mut self) -> Option<SectionIds> {
+        while let Some(&try_index) = self.next_try.last() {
+            let level: usize = self.chosen.len();
+            if try_index >= self.levels[level].len() {
+                //every section of this course has been tried, go back and change the previous pick
+                self.next_try.pop();
+                self.chosen.pop();
+                self.blocked.pop();
+                continue;
+            }
+            *self.next_try.last_mut().unwrap() += 1;
+
+            let candidate: usize = self.levels[level][try_index];
+            if self.blocked[level].contains(candidate) {
+                continue;
+            }
+
+            let mut blocked: BitSet = self.blocked[level].clone();
+            blocked.union_with(self.graph.conflicts_of(candidate));
+            self.chosen.push(candidate);
+
+            if self.chosen.len() == self.levels.len() {
+                //one of every course: this is a full schedule
+                let schedule: SectionIds = self.chosen.clone();
+                self.chosen.pop();
+                return Some(schedule);
+            }
+            //prune when a later course has nothing left that fits
+            if self.level_sets[level + 1..]
+                .iter()
+                .any(|set| !set.has_any_outside(&blocked))
+            {
+                self.chosen.pop();
+                continue;
+            }
+            self.blocked.push(blocked);
+            self.next_try.push(0);
+        }
+        None
+    }
+}
+
+///A finished schedule and its rating, ordered by rating so the worst kept one is easy to find.
+///Ties go to the schedule with the earlier course and section codes, the same order sort_rated uses
+struct Rated {
+    rating: f32,
+    //sorted, which puts them in course then section code order
+    ids: SectionIds,
+}
+
+impl PartialEq for Rated {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Rated {}
+
+impl PartialOrd for Rated {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rated {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rating
+            .total_cmp(&other.rating)
+            .then_with(|| other.ids.cmp(&self.ids))
+    }
+}
+
+///Branch and bound search that keeps only the best `k` schedules by rating.
+///A branch is dropped once an upper bound on any schedule it could still produce
+///can't beat the worst schedule kept so far
+pub struct TopKSearch<'a> {
+    graph: &'a ConflictGraph,
+    k: usize,
+    levels: Vec<&'a [usize]>,
+    //every usable alternate section
+    alternate_set: BitSet,
+    //each alternate course's sections as a set
+    alternate_courses: Vec<BitSet>,
+    //per section: professor rating and total soft constraint penalty
+    prof_ratings: Vec<f32>,
+    penalties: Vec<f32>,
+    //penalties that depend on the whole schedule, like days off
+    soft: Vec<SoftConstraint>,
+    //min heap of the best schedules found so far
+    best: BinaryHeap<Reverse<Rated>>,
+}
+
+impl<'a> TopKSearch<'a> {
+    pub fn new(graph: &'a ConflictGraph, constraints: &Constraints, k: usize) -> TopKSearch<'a> {
+        let levels: Vec<&[usize]> = course_levels(graph);
+
+        let mut alternate_set: BitSet = BitSet::new(graph.len());
+        let alternate_courses: Vec<BitSet> = graph
+            .alternate_courses()
+            .iter()
+            .map(|course| {
+                let mut set: BitSet = BitSet::new(graph.len());
+                for &id in graph.course_sections(course) {
+                    set.insert(id);
+                    alternate_set.insert(id);
+                }
+                set
+            })
+            .collect();
+
+        let prof_ratings: Vec<f32> = (0..graph.len())
+            .map(|id| graph.section(id).professor_rating())
+            .collect();
+        let penalties: Vec<f32> = (0..graph.len())
+            .map(|id| {
+                graph
+                    .section(id)
+                    .penalties(&constraints.soft)
+                    .iter()
+                    .map(|p| p.amount)
+                    .sum()
+            })
+            .collect();
+
+        TopKSearch {
+            graph,
+            k,
+            levels,
+            alternate_set,
+            alternate_courses,
+            prof_ratings,
+            penalties,
+            soft: constraints.soft.clone(),
+            best: BinaryHeap::new(),
+        }
+    }
+
+    ///Runs the search and returns the best schedules, highest rating first
+    pub fn run(mut self) -> Vec<SectionIds> {
+        if self.k == 0 || self.levels.is_empty() {
+            return Vec::new();
+        }
+        let mut chosen: Vec<usize> = Vec::new();
+        self.branch(&mut chosen, &BitSet::new(self.graph.len()));
+
+        let mut best: Vec<Rated> = self.best.into_iter().map(|Reverse(r)| r).collect();
+        best.sort_by(|a, b| b.cmp(a));
+        best.into_iter().map(|r| r.ids).collect()
+    }
+
+    fn branch(&mut self, chosen: &mut Vec<usize>, blocked: &BitSet) {
+        let level: usize = chosen.len();
+        if level == self.levels.len() {
+            self.keep(chosen);
+            return;
+        }
+        if self.best.len() == self.k
+            && let Some(Reverse(worst)) = self.best.peek()
+            //small slack so rounding in the bound never drops a schedule that would tie
+            && self.upper_bound(chosen, blocked) + 1e-3 <= worst.rating
+        {
+            return;
+        }
+
+        for &candidate in self.levels[level] {
+            if blocked.contains(candidate) {
+                continue;
+            }
+            let mut next_blocked: BitSet = blocked.clone();
+            next_blocked.union_with(self.graph.conflicts_of(candidate));
+            chosen.push(candidate);
+            self.branch(chosen, &next_blocked);
+            chosen.pop();
+        }
+    }
+
+    ///Rates a finished schedule and keeps it if it is among the best k so far
+    fn keep(&mut self, schedule: &[usize]) {
+        let alternates: Vec<Vec<usize>> = schedule
+            .iter()
+            .map(|&id| self.graph.find_alt(schedule, id))
+            .collect();
+        let slots: Vec<(&Section, Vec<&Section>)> = schedule
+            .iter()
+            .zip(&alternates)
+            .map(|(&id, alts)| {
+                (
+                    self.graph.section(id),
+                    alts.iter().map(|&a| self.graph.section(a)).collect(),
+                )
+            })
+            .collect();
+        let penalty: f32 = schedule.iter().map(|&id| self.penalties[id]).sum::<f32>()
+            + self.day_off_penalty(schedule);
+        let mut ids: SectionIds = schedule.to_vec();
+        ids.sort();
+        let rated: Rated = Rated {
+            rating: unpenalized_rating(&slots, self.graph.alternate_courses()) - penalty,
+            ids,
+        };
+
+        if self.best.len() < self.k {
+            self.best.push(Reverse(rated));
+        } else if let Some(mut worst) = self.best.peek_mut()
+            && rated > worst.0
+        {
+            *worst = Reverse(rated);
+        }
+    }
+
+    ///Days off lost to these sections, each counted once
+    fn day_off_penalty(&self, schedule: &[usize]) -> f32 {
+        let sections: Vec<&Section> = schedule.iter().map(|&id| self.graph.section(id)).collect();
+        day_off_penalties(&sections, &self.soft)
+            .iter()
+            .map(|(_, p)| p.amount)
+            .sum()
+    }
+
+    ///Highest rating any full schedule starting with `chosen` could get. Alternates only
+    ///disappear as sections are added, so each reward is bounded by the alternates still open
+    fn upper_bound(&self, chosen: &[usize], blocked: &BitSet) -> f32 {
+        let remaining: &[&[usize]] = &self.levels[chosen.len()..];
+
+        //best professor and smallest penalty still available for each course left to place
+        let mut prof_bound: f32 = chosen.iter().map(|&id| self.prof_ratings[id]).sum();
+        //days off already lost stay lost whatever else is added
+        let mut penalty_bound: f32 = chosen.iter().map(|&id| self.penalties[id]).sum::<f32>()
+            + self.day_off_penalty(chosen);
+        for level in remaining {
+            let open = level.iter().filter(|&&id| !blocked.contains(id));
+            prof_bound += open
+                .clone()
+                .map(|&id| self.prof_ratings[id])
+                .fold(f32::MIN, f32::max);
+            penalty_bound += open.map(|&id| self.penalties[id]).fold(f32::MAX, f32::min);
+        }
+
+        //alternates open to a section already chosen: those not blocked by the other chosen ones
+        let mut open_sets: Vec<BitSet> = chosen
+            .iter()
+            .map(|&replacing| {
+                let mut others: BitSet = BitSet::new(self.graph.len());
+                for &id in chosen.iter().filter(|&&id| id != replacing) {
+                    others.union_with(self.graph.conflicts_of(id));
+                }
+                others
+            })
+            .collect();
+        //sections still to be placed can only have alternates not blocked by anything chosen
+        open_sets.extend(remaining.iter().map(|_| blocked.clone()));
+
+        let mut best_alt_rating: f32 = 0.0;
+        let mut diversity_bound: f32 = 0.0;
+        for blocked_for_slot in &open_sets {
+            let counts: Vec<f32> = self
+                .alternate_courses
+                .iter()
+                .map(|course| course.count_outside(blocked_for_slot) as f32)
+                .collect();
+            diversity_bound += counts.iter().filter(|&&c| c > 0.0).count() as f32;
+            diversity_bound += upper_median(&counts);
+            best_alt_rating = self
+                .alternate_set
+                .iter_outside(blocked_for_slot)
+                .map(|id| self.prof_ratings[id])
+                .fold(best_alt_rating, f32::max);
+        }
+
+        prof_bound + best_alt_rating + diversity_bound - penalty_bound
+    }
+}
+
+///The median of the counts, or 0 when there are none
+fn upper_median(counts: &[f32]) -> f32 {
+    let mut counts: Vec<f32> = counts.to_vec();
+    counts.sort_by(|a, b| a.total_cmp(b));
+    let len: usize = counts.len();
+    if len == 0 {
+        0.0
+    } else if len % 2 == 1 {
+        counts[len / 2]
+    } else {
+        (counts[len / 2 - 1] + counts[len / 2]) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conflict::tests::{sample_constraints, sample_inputs};
+    use crate::schedule::{
+        get_potential_schedules, is_conflict, schedules_with_alternatives, with_alternates,
+    };
+    use crate::structs::{Blockout, ScheduleInputs, ScheduleWithAlternates};
+
+    ///Every pick of one section per required course with no two conflicting, found the slow way
+    fn brute_force(
+        graph: &ConflictGraph,
+        inputs: &ScheduleInputs,
+        constraints: &Constraints,
+    ) -> Vec<SectionIds> {
+        let mut schedules: Vec<SectionIds> = vec![Vec::new()];
+        for course in graph.desired_courses() {
+            schedules = schedules
+                .iter()
+                .flat_map(|schedule| {
+                    graph.course_sections(course).iter().map(move |&id| {
+                        let mut longer: SectionIds = schedule.clone();
+                        longer.push(id);
+                        longer
+                    })
+                })
+                .collect();
+        }
+        schedules.retain(|schedule| {
+            schedule.iter().enumerate().all(|(i, &a)| {
+                schedule[i + 1..].iter().all(|&b| {
+                    !is_conflict(
+                        graph.section(a),
+                        graph.section(b),
+                        inputs.walk.as_ref(),
+                        constraints,
+                    )
+                })
+            })
+        });
+        schedules.iter_mut().for_each(|schedule| schedule.sort());
+        schedules.sort();
+        schedules
+    }
+
+    fn sorted(mut schedules: Vec<SectionIds>) -> Vec<SectionIds> {
+        schedules.iter_mut().for_each(|schedule| schedule.sort());
+        schedules.sort();
+        schedules
+    }
+
+    #[test]
+    fn search_finds_every_schedule_once() {
+        let (inputs, constraints) = (sample_inputs(), sample_constraints());
+        let graph: ConflictGraph = ConflictGraph::new(&inputs, &constraints);
+        let expected: Vec<SectionIds> = brute_force(&graph, &inputs, &constraints);
+        assert_eq!(expected.len(), 25);
+        assert_eq!(sorted(ScheduleSearch::new(&graph).collect()), expected);
+    }
+
+    #[test]
+    fn splits_give_the_same_schedules_in_the_same_order() {
+        let (inputs, constraints) = (sample_inputs(), sample_constraints());
+        let graph: ConflictGraph = ConflictGraph::new(&inputs, &constraints);
+        let whole: Vec<SectionIds> = ScheduleSearch::new(&graph).collect();
+        let splits: Vec<ScheduleSearch> = ScheduleSearch::new(&graph).split();
+        assert_eq!(splits.len(), 2);
+        let split: Vec<SectionIds> = splits.into_iter().flatten().collect();
+        assert_eq!(split, whole);
+    }
+
+    #[test]
+    fn pruned_searches_still_match_brute_force() {
+        let inputs: ScheduleInputs = sample_inputs();
+        //each blockout leaves some course with fewer sections, down to none that fit
+        for (blockouts, count) in [
+            ("MWF:11:00-12:00", 4),
+            ("TuTh:9:00-10:00", 8),
+            ("TuTh:9:00-10:00,MWF:14:00-15:00", 0),
+            ("MW:9:00-17:00", 0),
+        ] {
+            let constraints: Constraints = Constraints {
+                blockouts: blockouts
+                    .split(',')
+                    .map(|b| Blockout::parse(b).unwrap())
+                    .collect(),
+                ..sample_constraints()
+            };
+            let graph: ConflictGraph = ConflictGraph::new(&inputs, &constraints);
+            let expected: Vec<SectionIds> = brute_force(&graph, &inputs, &constraints);
+            assert_eq!(expected.len(), count, "{}", blockouts);
+            assert_eq!(
+                sorted(ScheduleSearch::new(&graph).collect()),
+                expected,
+                "{}",
+                blockouts
+            );
+            let split: Vec<SectionIds> = ScheduleSearch::new(&graph)
+                .split()
+                .into_iter()
+                .flatten()
+                .collect();
+            assert_eq!(sorted(split), expected, "{}", blockouts);
+        }
+    }
+
+    #[test]
+    fn nothing_asked_for_finds_nothing() {
+        let mut inputs: ScheduleInputs = sample_inputs();
+        inputs.desired_courses.clear();
+        let graph: ConflictGraph = ConflictGraph::new(&inputs, &sample_constraints());
+        assert_eq!(ScheduleSearch::new(&graph).count(), 0);
+        assert!(ScheduleSearch::new(&graph).split().is_empty());
+    }
+
+    ///Course and section codes of each schedule, to compare schedules found different ways
+    fn codes(schedules: &[ScheduleWithAlternates]) -> Vec<Vec<String>> {
+        schedules
+            .iter()
+            .map(|schedule| {
+                schedule
+                    .iter()
+                    .map(|(s, _)| format!("{}-{}", s.course, s.section))
+                    .collect()
+            })
+            .collect()
+    }
+
+    ///Top k schedules from the branch and bound search, against the first k of every schedule rated
+    fn assert_top_k_matches_full_search(inputs: &ScheduleInputs, constraints: &Constraints) {
+        let graph: ConflictGraph = ConflictGraph::new(inputs, constraints);
+        let all: Vec<ScheduleWithAlternates> =
+            schedules_with_alternatives(&graph, get_potential_schedules(&graph), constraints);
+        for k in [1, 2, 5, 10, all.len(), all.len() + 5] {
+            let top: Vec<ScheduleWithAlternates> = TopKSearch::new(&graph, constraints, k)
+                .run()
+                .iter()
+                .map(|ids| with_alternates(&graph, ids))
+                .collect();
+            assert_eq!(codes(&top), codes(&all[..k.min(all.len())]), "k = {}", k);
+        }
+    }
+
+    #[test]
+    fn top_k_keeps_the_best_rated_schedules() {
+        assert_top_k_matches_full_search(&sample_inputs(), &sample_constraints());
+    }
+
+    #[test]
+    fn top_k_breaks_ties_like_sort_rated() {
+        //nobody rated and no alternates or preferences: every schedule rates the same
+        let mut inputs: ScheduleInputs = sample_inputs();
+        inputs.alternate_courses.clear();
+        for section in inputs
+            .desired_courses
+            .values_mut()
+            .flat_map(|s| s.values_mut())
+        {
+            section.professors.clear();
+        }
+        assert_top_k_matches_full_search(&inputs, &Constraints::default());
+        //with only a day off, schedules tie in groups
+        let constraints: Constraints = Constraints {
+            soft: vec![SoftConstraint::parse_day_off("F:2").unwrap()],
+            ..Constraints::default()
+        };
+        assert_top_k_matches_full_search(&inputs, &constraints);
+    }
+
+    #[test]
+    fn nothing_kept_for_k_of_zero() {
+        let (inputs, constraints) = (sample_inputs(), sample_constraints());
+        let graph: ConflictGraph = ConflictGraph::new(&inputs, &constraints);
+        assert!(TopKSearch::new(&graph, &constraints, 0).run().is_empty());
+    }
+}
@@ -0,0 +1,365 @@
+use crate::error::{FetchError, FetchResult};
+use crate::http::HttpClient;
+use crate::time::{TimeOfDay, Weekday};
+use crate::structs::*;
+use futures::future::BoxFuture;
+use reqwest::{Response, StatusCode};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+///Somewhere the sections of a course can be looked up.
+///Sections come back without professor ratings unless the source already has them
+pub trait CourseSource: Send + Sync {
+    ///Sections of the course in the semester, keyed by section code
+    fn sections<'a>(&'a self, course: &'a str, semester: &'a str) -> BoxFuture<'a, FetchResult>;
+
+    ///Whether what this source returns is worth keeping in the course cache
+    fn cacheable(&self) -> bool {
+        true
+    }
+}
+
+pub const UMD_IO_URL: &str = "https://api.umd.io";
+
+///The umd.io course API, or anything serving the same paths at `base_url`
+pub struct UmdIo {
+    http: HttpClient,
+    base_url: String,
+}
+
+impl UmdIo {
+    pub fn new(base_url: &str, http: HttpClient) -> UmdIo {
+        UmdIo {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl CourseSource for UmdIo {
+    fn sections<'a>(&'a self, course: &'a str, semester: &'a str) -> BoxFuture<'a, FetchResult> {
+        Box::pin(async move {
+            let response: Response = self
+                .http
+                .get(&format!(
+                    "{}/v1/courses/{}/sections?semester={}",
+                    self.base_url, course, semester
+                ))
+                .await?;
+            //umd.io answers 404 for courses it doesn't have
+            if response.status() == StatusCode::NOT_FOUND {
+                return Err(FetchError::UnknownCourse(
+                    course.to_string(),
+                    semester.to_string(),
+                ));
+            }
+            let raw: String = FetchError::check(response)?.text().await?;
+            //collection data into the input struct
+            let input_list: Vec<SectionInput> =
+                FetchError::parse(&raw, &format!("umd.io sections of {}", course))?;
+            Ok(from_umd(input_list))
+        })
+    }
+}
+
+///Formats sections as umd.io returns them into a SectionMap
+fn from_umd(input_list: Vec<SectionInput>) -> SectionMap {
+    let mut output_map: SectionMap = SectionMap::new();
+
+    //format it into the output
+    for section_input in input_list {
+        //iterate through each section
+        //format the Classtimes struct
+        let mut classtimes: Classtimes = Classtimes::new();
+        let mut unscheduled: Vec<Unscheduled> = Vec::new();
+        let mut add_unscheduled = |kind: Unscheduled| {
+            if !unscheduled.contains(&kind) {
+                unscheduled.push(kind);
+            }
+        };
+        for meeting in section_input.meetings {
+            //iterate through each meeting group
+            if let Some(kind) = unscheduled_kind(&meeting) {
+                add_unscheduled(kind);
+                continue;
+            }
+            //a meeting with times or days that can't be read can't be placed in a week. It is
+            //kept as TBA, so it is warned about like any other meeting that can't be checked
+            let (start, end, days) = match (
+                TimeOfDay::parse_12h(&meeting.start_time),
+                TimeOfDay::parse_12h(&meeting.end_time),
+                Weekday::parse_days(&meeting.days),
+            ) {
+                (Ok(start), Ok(end), Ok(days)) => (start, end, days),
+                (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+                    eprintln!(
+                        "Treating a meeting of {}-{} as TBA: {}",
+                        section_input.course, section_input.number, e
+                    );
+                    add_unscheduled(Unscheduled::Tba);
+                    continue;
+                }
+            };
+            // format the start and end time of this meet group
+            //online meetings at a set time still clash, but there is nowhere to walk to
+            let building: String = match is_online(&meeting) {
+                true => String::new(),
+                false => meeting.building,
+            };
+            let start_end: StartEnd = StartEnd {
+                building,
+                start,
+                end,
+            };
+            //use the times and days to add an entries to the classtimes property for this section
+            for day in days {
+                if let Some(day_meetings) = classtimes.get_mut(&day) {
+                    day_meetings.push(start_end.clone());
+                } else {
+                    classtimes.insert(day, vec![start_end.clone()]);
+                }
+            }
+        }
+
+        let course_name: String = section_input.course;
+        let section_name: String = section_input.number;
+        //co-taught sections list every instructor, each rated on their own later
+        let professors: Vec<ProfData> = section_input
+            .instructors
+            .into_iter()
+            .map(|name| ProfData { name, rating: 0.0 })
+            .collect();
+        let seats: [u32; 3] = [
+            section_input.seats.parse().unwrap_or_default(),
+            section_input.open_seats.parse().unwrap_or_default(),
+            section_input.waitlist.parse().unwrap_or_default(),
+        ];
+
+        //compile section struct, formatted for output. The ratings are filled in later
+        let section_formatted: Section = Section {
+            professors,
+            classtimes,
+            unscheduled,
+            course: course_name,
+            section: section_name.clone(),
+            seats,
+        };
+        output_map.insert(section_name, section_formatted);
+    }
+
+    return output_map;
+}
+
+fn is_online(meeting: &MeetTime) -> bool {
+    meeting.building.eq_ignore_ascii_case("ONLINE")
+}
+
+///Whether a meeting is held without a set time, and why.
+///umd.io leaves the days and times blank (or writes TBA) until they are announced,
+///and asynchronous online meetings never get any
+fn unscheduled_kind(meeting: &MeetTime) -> Option<Unscheduled> {
+    let blank = |field: &str| field.trim().is_empty() || field.trim().eq_ignore_ascii_case("TBA");
+    let asynchronous: bool = meeting.room.to_ascii_uppercase().contains("ASYNC");
+    let untimed: bool =
+        blank(&meeting.days) || blank(&meeting.start_time) || blank(&meeting.end_time);
+    match (is_online(meeting), asynchronous || untimed) {
+        (true, true) => Some(Unscheduled::Online),
+        (false, _) if untimed => Some(Unscheduled::Tba),
+        _ => None,
+    }
+}
+
+///A directory of json files laid out as `<dir>/<semester>/<COURSE>.json`, each either the
+///sections as umd.io returns them (like the files in fixtures/umd) or a saved SectionMap
+pub struct JsonDir {
+    dir: PathBuf,
+}
+
+impl JsonDir {
+    pub fn new(dir: PathBuf) -> JsonDir {
+        JsonDir { dir }
+    }
+}
+
+impl CourseSource for JsonDir {
+    fn sections<'a>(&'a self, course: &'a str, semester: &'a str) -> BoxFuture<'a, FetchResult> {
+        Box::pin(async move {
+            let path: PathBuf = self.dir.join(semester).join(format!("{}.json", course));
+            let raw: String = match fs::read_to_string(&path) {
+                Ok(raw) => raw,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    return Err(FetchError::UnknownCourse(
+                        course.to_string(),
+                        semester.to_string(),
+                    ));
+                }
+                Err(e) => return Err(FetchError::Io(path, e)),
+            };
+            //umd.io lists sections in an array, a SectionMap is an object
+            let what: String = path.display().to_string();
+            match raw.trim_start().starts_with('[') {
+                true => Ok(from_umd(FetchError::parse(&raw, &what)?)),
+                false => FetchError::parse(&raw, &what),
+            }
+        })
+    }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
+}
+
+///Courses held in memory, the same for every semester.
+///Used for tests and for replaying the output of the fetch command
+pub struct MockSource {
+    courses: CourseMap,
+}
+
+impl MockSource {
+    pub fn new(courses: CourseMap) -> MockSource {
+        MockSource { courses }
+    }
+
+    ///Loads a CourseMap json file, like the one the fetch command prints
+    pub fn from_file(path: &Path) -> Result<MockSource, Box<dyn std::error::Error>> {
+        let raw: String = fs::read_to_string(path)?;
+        Ok(MockSource::new(serde_json::from_str(&raw)?))
+    }
+}
+
+impl CourseSource for MockSource {
+    fn sections<'a>(&'a self, course: &'a str, semester: &'a str) -> BoxFuture<'a, FetchResult> {
+        Box::pin(async move {
+            match self.courses.get(course) {
+                Some(sections) => Ok(sections.clone()),
+                None => Err(FetchError::UnknownCourse(
+                    course.to_string(),
+                    semester.to_string(),
+                )),
+            }
+        })
+    }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
+}
+
+///Which course source a run uses, as given on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceSpec {
+    Umd,
+    Dir(PathBuf),
+    File(PathBuf),
+}
+
+impl SourceSpec {
+    ///Parses `umd`, `dir:PATH` or `file:PATH`
+    pub fn parse(s: &str) -> Result<SourceSpec, String> {
+        match s.split_once(':') {
+            None if s == "umd" => Ok(SourceSpec::Umd),
+            Some(("dir", path)) if !path.is_empty() => Ok(SourceSpec::Dir(PathBuf::from(path))),
+            Some(("file", path)) if !path.is_empty() => Ok(SourceSpec::File(PathBuf::from(path))),
+            _ => Err(format!(
+                "unknown course source {}, expected umd, dir:PATH or file:PATH",
+                s
+            )),
+        }
+    }
+
+    ///Opens the source, with umd.io served from `umd_url` through the shared client
+    pub fn open(
+        &self,
+        umd_url: &str,
+        http: &HttpClient,
+    ) -> Result<Box<dyn CourseSource>, Box<dyn std::error::Error>> {
+        Ok(match self {
+            SourceSpec::Umd => Box::new(UmdIo::new(umd_url, http.clone())),
+            SourceSpec::Dir(dir) => Box::new(JsonDir::new(dir.clone())),
+            SourceSpec::File(path) => Box::new(MockSource::from_file(path)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn dir_reads_umd_io_files() {
+        let source: JsonDir = JsonDir::new(PathBuf::from("fixtures/umd"));
+        let sections: SectionMap = source.sections("PHYS260", "202601").await.unwrap();
+        let lecture: &Section = &sections["0101"];
+        assert_eq!(lecture.course, "PHYS260");
+        assert_eq!(lecture.professors[0].name, "Ada Lovelace");
+        assert_eq!(lecture.classtimes[&Weekday::Tuesday][0].building, "PHY");
+        assert!(matches!(
+            source.sections("PHYS999", "202601").await,
+            Err(FetchError::UnknownCourse(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn dir_reads_saved_sections() {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("json-dir-test-{}", std::process::id()));
+        let umd: SectionMap = JsonDir::new(PathBuf::from("fixtures/umd"))
+            .sections("PHYS260", "202601")
+            .await
+            .unwrap();
+        fs::create_dir_all(dir.join("202601")).unwrap();
+        fs::write(
+            dir.join("202601/PHYS260.json"),
+            serde_json::to_string(&umd).unwrap(),
+        )
+        .unwrap();
+
+        let saved: FetchResult = JsonDir::new(dir.clone())
+            .sections("PHYS260", "202601")
+            .await;
+        fs::remove_dir_all(&dir).unwrap();
+        let saved: SectionMap = saved.unwrap();
+        assert_eq!(
+            saved.keys().collect::<Vec<_>>(),
+            umd.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(saved["0101"].classtimes.len(), umd["0101"].classtimes.len());
+    }
+
+    fn umd_section(meetings: serde_json::Value) -> SectionMap {
+        let input: SectionInput = serde_json::from_value(serde_json::json!({
+            "course": "PHYS260", "number": "0101", "seats": "30", "open_seats": "4",
+            "waitlist": "0", "instructors": ["Ada Lovelace"], "meetings": meetings
+        }))
+        .unwrap();
+        from_umd(vec![input])
+    }
+
+    #[test]
+    fn unreadable_meetings_are_tba() {
+        let sections: SectionMap = umd_section(serde_json::json!([
+            {"days": "MWF", "building": "PHY", "start_time": "9:00am", "end_time": "9:50am"},
+            {"days": "Tu", "building": "PHY", "start_time": "10:00", "end_time": "10:50am"},
+            {"days": "Xy", "building": "PHY", "start_time": "1:00pm", "end_time": "1:50pm"}
+        ]));
+        let section: &Section = &sections["0101"];
+        assert_eq!(section.unscheduled, [Unscheduled::Tba]);
+        assert!(section.has_tba());
+        assert_eq!(section.classtimes.len(), 3);
+        assert!(!section.classtimes.contains_key(&Weekday::Tuesday));
+    }
+
+    #[test]
+    fn blank_and_online_meetings() {
+        let sections: SectionMap = umd_section(serde_json::json!([
+            {"days": "", "building": "PHY", "start_time": "", "end_time": ""},
+            {"days": "", "building": "ONLINE", "room": "ASYNC", "start_time": "", "end_time": ""},
+            {"days": "Th", "building": "ONLINE", "start_time": "2:00pm", "end_time": "3:15pm"}
+        ]));
+        let section: &Section = &sections["0101"];
+        assert_eq!(section.unscheduled, [Unscheduled::Tba, Unscheduled::Online]);
+        //online at a set time still clashes, with nowhere to walk to
+        assert_eq!(section.classtimes[&Weekday::Thursday][0].building, "");
+    }
+}
@@ -1,11 +1,15 @@
-use crate::schedule::{is_conflict, un_military_time};
+use crate::buildings::UnknownBuildings;
+use crate::schedule::{ARRIVAL_BUFFER, EARLIST, LATEST, WALK_SPEED, is_conflict};
+use crate::time::{TimeOfDay, Weekday};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crate::walk::WalkModel;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 //
-pub type CourseMap = HashMap<String, SectionMap>;
-pub type SectionMap = HashMap<String, Section>;
-pub type Classtimes = HashMap<u32, Vec<StartEnd>>;
+pub type CourseMap = BTreeMap<String, SectionMap>;
+pub type SectionMap = BTreeMap<String, Section>;
+pub type Classtimes = BTreeMap<Weekday, Vec<StartEnd>>;
 pub type ClasstimesForHumans = Vec<String>;
 pub type ScheduleWithAlternates = Vec<(Section, Vec<Section>)>; // a schedule where each section has a list of alternates
 pub type BuildingMap = HashMap<String, BuildingData>;
@@ -14,21 +18,36 @@ pub type DisplaySchedule = Vec<DisplaySection>;
 pub type Schedule = Vec<Section>;
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Section {
-    pub professor: ProfData,
+    //everyone teaching the section, empty when umd.io lists nobody
+    #[serde(alias = "professor", deserialize_with = "one_or_many_professors")]
+    pub professors: Vec<ProfData>,
     pub classtimes: Classtimes,
+    //meetings with no place in the week, which can't conflict with anything
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unscheduled: Vec<Unscheduled>,
     pub course: String,
     pub section: String,
     pub seats: [u32; 3], //Total, open, waitlisted
 }
 
+///A meeting that isn't held at a set time
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Unscheduled {
+    ///Its days or times haven't been announced yet, so it may still clash once they are
+    Tba,
+    ///Online and asynchronous, never held at a set time
+    Online,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StartEnd {
     pub building: String,
-    pub start: u32,
-    pub end: u32,
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct BuildingData {
     //pub name: String,
     //pub id: String,
@@ -42,14 +61,139 @@ pub struct ProfData {
     pub rating: f32,
 }
 
+///Reads the professor list, or a single professor the way sections were saved before co-taught
+///sections kept every instructor. A lone "Unknown" professor means nobody was listed
+fn one_or_many_professors<'de, D>(deserializer: D) -> Result<Vec<ProfData>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Professors {
+        Many(Vec<ProfData>),
+        One(ProfData),
+    }
+    Ok(match Professors::deserialize(deserializer)? {
+        Professors::Many(professors) => professors,
+        Professors::One(professor) if professor.name == "Unknown" => Vec::new(),
+        Professors::One(professor) => vec![professor],
+    })
+}
+
+///Names of the professors for people, "Unknown" when there are none
+pub fn professor_names(professors: &[ProfData]) -> String {
+    match professors.is_empty() {
+        true => String::from("Unknown"),
+        false => professors
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", "),
+    }
+}
+
+///The limits a schedule has to fit inside, chosen per student
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Constraints {
+    //walk speed in meters per second
+    pub walk_speed: f32,
+    //earlist and latest time to go to class on any day
+    pub earliest: TimeOfDay,
+    pub latest: TimeOfDay,
+    //overrides for specific days, keyed by day (M, Tu, W, Th, F, Sa, Su)
+    pub days: BTreeMap<String, DayWindow>,
+    //busy periods no section may overlap
+    pub blockouts: Vec<Blockout>,
+    //preferences that lower a schedule's rating instead of ruling it out
+    pub soft: Vec<SoftConstraint>,
+    //what to assume about buildings missing from the building file
+    pub unknown_buildings: UnknownBuildings,
+    //minutes early a student wants to get to their next class
+    pub arrival_buffer: u32,
+}
+
+///A preference that costs `weight` rating points each time a schedule goes against it
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SoftConstraint {
+    ///Each meeting overlapping start-end on one of the days costs weight
+    Window {
+        days: String,
+        start: TimeOfDay,
+        end: TimeOfDay,
+        weight: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    },
+    ///Costs weight once if any section meets on the day
+    DayOff { day: String, weight: f32 },
+    ///Each meeting in the building costs weight
+    AvoidBuilding { building: String, weight: f32 },
+}
+
+///One reason a section lowered its schedule's rating
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Penalty {
+    pub reason: String,
+    pub amount: f32,
+}
+
+///A period the student is busy every week (work, practice, lunch...), treated like an immovable section
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Blockout {
+    //days it happens on, same letters as the course API (e.g. MWF, TuTh)
+    pub days: String,
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    //building code, used to check walking to and from it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+///Earliest/latest override for a single day. Missing values fall back to the everyday ones
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct DayWindow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub earliest: Option<TimeOfDay>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest: Option<TimeOfDay>,
+}
+
+///The courses, buildings and constraints needed to generate schedules
+#[derive(Debug, Clone)]
+pub struct ScheduleInputs {
+    pub desired_courses: CourseMap,
+    pub alternate_courses: CourseMap,
+    pub buildings: BuildingMap,
+    //walking times between those buildings
+    pub walk: Arc<dyn WalkModel>,
+}
+
+///How a run generates schedules, as opposed to what the schedules must satisfy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerateOptions {
+    //only keep this many of the best rated schedules
+    pub top: Option<usize>,
+    //worker threads for generation and alternates, 1 runs everything on this thread
+    pub threads: usize,
+}
+
 #[derive(Debug, Serialize, Clone, Default)]
 pub struct DisplaySection {
-    pub professor: ProfData,
+    pub professors: Vec<ProfData>,
+    //the section's combined rating, as used in scoring
+    pub professor_rating: f32,
     pub classtimes: ClasstimesForHumans,
     pub course: String,
     pub section: String,
     pub seats: [u32; 3], //Total, open, waitlisted
     pub alternates: String,
+    pub penalties: Vec<Penalty>,
 }
 
 //The next two types are the onces that the course API returns
@@ -69,7 +213,8 @@ pub struct SectionInput {
 #[derive(Debug, Deserialize)]
 pub struct MeetTime {
     pub days: String,
-    //room: String,
+    #[serde(default)]
+    pub room: String,
     pub building: String,
     //classtype: String,
     pub start_time: String,
@@ -80,7 +225,8 @@ pub struct MeetTime {
 #[derive(Debug, Deserialize, Default, PartialEq)]
 pub struct ProfRatingInput {
     //courses: Vec<String>,
-    pub average_rating: f32,
+    //null for professors without reviews
+    pub average_rating: Option<f32>,
     //type: String,
     //name: String,
     //slug: String,
@@ -93,99 +239,477 @@ impl PartialEq for Section {
 }
 
 impl Section {
-    ///Finds an alternate sections that can replace this section in the given schedule
-    pub fn find_alt(
-        &self,
-        mut schedule: Vec<Section>,
-        buildings: &HashMap<String, BuildingData>,
-        walk_speed: f32,
-        earliest: u32,
-        latest: u32,
-        alternates: &CourseMap,
-    ) -> Vec<Section> {
-        //remove the course in question
-        schedule.retain(|s| s != self);
-
-        //test every alternate and keep track of the ones that fit properly
-        let mut alts: Vec<Section> = Vec::new();
-        for (_, alt_section_map) in alternates {
-            //for each alternate course
-            'section_loop: for (_, alt_section) in alt_section_map {
-                //for each section in that alternate course
-                for current_section in &schedule {
-                    //see if the alternate section conflicts with any other section in the current schedule
-                    if is_conflict(
-                        current_section,
-                        alt_section,
-                        buildings,
-                        walk_speed,
-                        earliest,
-                        latest,
-                    ) {
-                        continue 'section_loop; //if this section conflicts with anything in the schedule, move on to the next section
+    ///Whether some of the section's meetings don't have their times yet
+    pub fn has_tba(&self) -> bool {
+        self.unscheduled.contains(&Unscheduled::Tba)
+    }
+
+    ///The rating a section counts for in scoring: the average of its rated professors.
+    ///Professors without a rating are left out rather than counted as 0, and a section
+    ///where nobody is rated counts 0 like a section without professors
+    pub fn professor_rating(&self) -> f32 {
+        let rated: Vec<f32> = self
+            .professors
+            .iter()
+            .map(|p| p.rating)
+            .filter(|rating| *rating != 0.0)
+            .collect();
+        match rated.is_empty() {
+            true => 0.0,
+            false => rated.iter().sum::<f32>() / rated.len() as f32,
+        }
+    }
+    ///Checks that every meeting of this section starts and ends inside the allowed window for its day
+    pub fn fits_window(&self, constraints: &Constraints) -> bool {
+        self.classtimes.iter().all(|(day, times)| {
+            let (earliest, latest) = constraints.window(*day);
+            times.iter().all(|t| t.start >= earliest && t.end <= latest)
+        })
+    }
+    ///Checks that this section fits the day windows and doesn't run into any blockout
+    pub fn is_allowed(&self, walk: &dyn WalkModel, constraints: &Constraints) -> bool {
+        self.fits_window(constraints)
+            && !constraints
+                .blockouts
+                .iter()
+                .any(|b| is_conflict(&b.to_section(), self, walk, constraints))
+    }
+    ///Lists every soft constraint this section goes against on its own and what it costs.
+    ///Days off depend on the whole schedule, see schedule::day_off_penalties
+    pub fn penalties(&self, soft: &[SoftConstraint]) -> Vec<Penalty> {
+        let name: String = format!("{}-{}", self.course, self.section);
+        let mut penalties: Vec<Penalty> = Vec::new();
+        for constraint in soft {
+            match constraint {
+                SoftConstraint::Window {
+                    days,
+                    start,
+                    end,
+                    weight,
+                    label,
+                } => {
+                    let label: String = label.clone().unwrap_or_else(|| {
+                        format!("{}-{}", start, end)
+                    });
+                    for day in Weekday::parse_days(days).unwrap_or_default() {
+                        for time in self.classtimes.get(&day).into_iter().flatten() {
+                            if time.start < *end && time.end > *start {
+                                penalties.push(Penalty {
+                                    reason: format!(
+                                        "{} meets during {} on {}",
+                                        name,
+                                        label, day
+                                    ),
+                                    amount: *weight,
+                                });
+                            }
+                        }
+                    }
+                }
+                //a day off is lost once per schedule, however many sections meet on it
+                SoftConstraint::DayOff { .. } => {}
+                SoftConstraint::AvoidBuilding { building, weight } => {
+                    for (day, times) in &self.classtimes {
+                        for time in times.iter().filter(|t| &t.building == building) {
+                            penalties.push(Penalty {
+                                reason: format!(
+                                    "{} meets in {} on {}",
+                                    name,
+                                    time.building, day
+                                ),
+                                amount: *weight,
+                            });
+                        }
                     }
                 }
-                //if we reach here, that means this alternate is compatible with the whole schedule
-                alts.push(alt_section.clone());
             }
         }
-
-        return alts;
+        penalties
     }
-    ///Takes class times stored with numbers for computers to stored by days for humans
+    ///Takes class times stored with numbers for computers to stored by days for humans.
+    ///Meetings are listed in the order they first happen in the week
     pub fn humanize_times(&self) -> ClasstimesForHumans {
-        let mut classtimes_human: HashMap<String, Vec<String>> = HashMap::new();
-        let key: HashMap<String, usize> = HashMap::from([
-            ("M".to_string(), 0),
-            ("Tu".to_string(), 1),
-            ("W".to_string(), 2),
-            ("Th".to_string(), 3),
-            ("F".to_string(), 4),
-        ]);
-        for (day_num, times) in &self.classtimes {
-            let day_str: &'static str = match day_num {
-                1 => "M",
-                2 => "Tu",
-                3 => "W",
-                4 => "Th",
-                5 => "F",
-                _ => "Unknown",
-            };
+        //classtimes is ordered by day, so days are added in chronological order
+        let mut classtimes_human: Vec<(String, Vec<&'static str>)> = Vec::new();
+        for (day, times) in &self.classtimes {
+            let day_str: &'static str = day.short_name();
 
             for time in times {
                 let time_str = format!(
                     "{}-{} in {}",
-                    un_military_time(time.start),
-                    un_military_time(time.end),
-                    time.building
+                    time.start, time.end, time.building
                 );
-                if let Some(meeting) = classtimes_human.get_mut(&time_str) {
-                    meeting.push(day_str.to_string());
-                } else {
-                    classtimes_human.insert(time_str, vec![day_str.to_string()]);
+                match classtimes_human.iter_mut().find(|(t, _)| *t == time_str) {
+                    Some((_, days)) => days.push(day_str),
+                    None => classtimes_human.push((time_str, vec![day_str])),
                 }
             }
         }
 
-        //sort days in chronological order
-        for (_, days) in classtimes_human.iter_mut() {
-            let mut days_sorted: Vec<String> = vec![
-                String::new(),
-                String::new(),
-                String::new(),
-                String::new(),
-                String::new(),
-            ];
-            for day in days.iter() {
-                days_sorted[key[day.as_str()]] = day.to_string();
-            }
-            days_sorted.retain(|d| !d.is_empty());
-            *days = days_sorted;
-        }
-
+        let unscheduled = self.unscheduled.iter().map(|meeting| match meeting {
+            Unscheduled::Tba => String::from("Times to be announced, not checked for conflicts"),
+            Unscheduled::Online => String::from("Online, asynchronous"),
+        });
         return classtimes_human
             .into_iter()
             .map(|(time, days)| days.join("") + " " + &time)
+            .chain(unscheduled)
             .collect();
     }
 }
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Constraints {
+            walk_speed: WALK_SPEED,
+            earliest: EARLIST,
+            latest: LATEST,
+            days: BTreeMap::new(),
+            blockouts: Vec::new(),
+            soft: Vec::new(),
+            unknown_buildings: UnknownBuildings::default(),
+            arrival_buffer: ARRIVAL_BUFFER,
+        }
+    }
+}
+
+impl DayWindow {
+    ///Parses a day window written as DAY:EARLIEST-LATEST (e.g. F:1000-1600), either time may be left blank
+    pub fn parse(spec: &str) -> Result<(String, DayWindow), String> {
+        let parse_time = |time: &str| -> Result<Option<TimeOfDay>, String> {
+            if time.is_empty() {
+                Ok(None)
+            } else {
+                time.parse().map(Some)
+            }
+        };
+
+        let (day, times) = spec
+            .split_once(':')
+            .ok_or_else(|| format!("'{}' should look like F:1000-1600", spec))?;
+        let (earliest, latest) = times.split_once('-').unwrap_or((times, ""));
+        Ok((
+            day.to_string(),
+            DayWindow {
+                earliest: parse_time(earliest)?,
+                latest: parse_time(latest)?,
+            },
+        ))
+    }
+}
+
+///Splits a time off the front of `spec`, along with whatever follows it after a ':'.
+///The time may be HH:MM itself, so the longest start that reads as a time is taken
+///(13:00:Lunch gives 13:00 and Lunch)
+fn leading_time(spec: &str) -> Option<(TimeOfDay, Option<&str>)> {
+    if let Ok(time) = spec.parse() {
+        return Some((time, None));
+    }
+    spec.match_indices(':')
+        .rev()
+        .find_map(|(i, _)| Some((spec[..i].parse().ok()?, Some(&spec[i + 1..]))))
+}
+
+///Splits DAYS:START-END... into the days, the start, the end and whatever follows the end
+fn days_and_times(spec: &str) -> Option<(String, TimeOfDay, TimeOfDay, Option<&str>)> {
+    let (days, times) = spec.split_once(':')?;
+    let (start, rest) = times.split_once('-')?;
+    let (end, rest) = leading_time(rest)?;
+    Some((days.to_string(), start.parse().ok()?, end, rest))
+}
+
+impl Blockout {
+    ///Parses a blockout written as DAYS:START-END, optionally followed by :LABEL and/or @LOCATION
+    ///(e.g. MWF:1200-1300:Lunch@STAMP or MWF:12:00-13:00:Lunch@STAMP)
+    pub fn parse(spec: &str) -> Result<Blockout, String> {
+        let bad = || format!("'{}' should look like MWF:1200-1300:Lunch@STAMP", spec);
+        let (spec_no_loc, location) = match spec.rsplit_once('@') {
+            Some((rest, loc)) => (rest, Some(loc.to_string())),
+            None => (spec, None),
+        };
+        let (days, start, end, label) = days_and_times(spec_no_loc).ok_or_else(bad)?;
+        Ok(Blockout {
+            days,
+            start,
+            end,
+            label: label.map(|l| l.to_string()),
+            location,
+        })
+    }
+
+    ///Writes the blockout back in the form parse reads
+    pub fn spec(&self) -> String {
+        let mut spec: String = format!("{}:{}-{}", self.days, self.start.hhmm(), self.end.hhmm());
+        if let Some(label) = &self.label {
+            spec += &format!(":{}", label);
+        }
+        if let Some(location) = &self.location {
+            spec += &format!("@{}", location);
+        }
+        spec
+    }
+
+    ///Turns the blockout into a section so it can be conflict checked like any other
+    pub fn to_section(&self) -> Section {
+        let meeting: StartEnd = StartEnd {
+            building: self.location.clone().unwrap_or_default(),
+            start: self.start,
+            end: self.end,
+        };
+        Section {
+            professors: Vec::new(),
+            unscheduled: Vec::new(),
+            classtimes: Weekday::parse_days(&self.days)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|day| (day, vec![meeting.clone()]))
+                .collect(),
+            course: self.label.clone().unwrap_or(String::from("Blockout")),
+            section: String::new(),
+            seats: [0, 0, 0],
+        }
+    }
+
+    ///Describes the blockout for people, e.g. "MW 12:00pm-1:00pm Lunch in STAMP"
+    pub fn describe(&self) -> String {
+        let mut description: String = format!(
+            "{} {}-{}",
+            self.days, self.start, self.end
+        );
+        if let Some(label) = &self.label {
+            description += &format!(" {}", label);
+        }
+        if let Some(location) = &self.location {
+            description += &format!(" in {}", location);
+        }
+        description
+    }
+}
+
+impl SoftConstraint {
+    ///Parses a soft window written as DAYS:START-END:WEIGHT[:LABEL]
+    ///(e.g. MTuWThF:1130-1300:2:lunch or MTuWThF:11:30-13:00:2:lunch)
+    pub fn parse_window(spec: &str) -> Result<SoftConstraint, String> {
+        let bad = || format!("'{}' should look like MTuWThF:1130-1300:2:lunch", spec);
+        let (days, start, end, rest) = days_and_times(spec).ok_or_else(bad)?;
+        let rest: &str = rest.ok_or_else(bad)?;
+        let (weight, label) = match rest.split_once(':') {
+            Some((weight, label)) => (weight, Some(label.to_string())),
+            None => (rest, None),
+        };
+        Ok(SoftConstraint::Window {
+            days,
+            start,
+            end,
+            weight: weight.parse().map_err(|_| bad())?,
+            label,
+        })
+    }
+
+    ///Parses a preferred day off written as DAY:WEIGHT (e.g. F:3)
+    pub fn parse_day_off(spec: &str) -> Result<SoftConstraint, String> {
+        let bad = || format!("'{}' should look like F:3", spec);
+        let (day, weight) = spec.split_once(':').ok_or_else(bad)?;
+        Ok(SoftConstraint::DayOff {
+            day: day.to_string(),
+            weight: weight.parse().map_err(|_| bad())?,
+        })
+    }
+
+    ///Parses a building to avoid written as BUILDING:WEIGHT (e.g. ESJ:1)
+    pub fn parse_avoid_building(spec: &str) -> Result<SoftConstraint, String> {
+        let bad = || format!("'{}' should look like ESJ:1", spec);
+        let (building, weight) = spec.split_once(':').ok_or_else(bad)?;
+        Ok(SoftConstraint::AvoidBuilding {
+            building: building.to_string(),
+            weight: weight.parse().map_err(|_| bad())?,
+        })
+    }
+}
+
+impl Constraints {
+    pub fn arrival_buffer_seconds(&self) -> f32 {
+        //in f32, so a huge buffer can't overflow
+        self.arrival_buffer as f32 * 60.0
+    }
+
+    ///Earliest start and latest end allowed on the given day
+    pub fn window(&self, day: Weekday) -> (TimeOfDay, TimeOfDay) {
+        match self.days.get(day.short_name()) {
+            Some(window) => (
+                window.earliest.unwrap_or(self.earliest),
+                window.latest.unwrap_or(self.latest),
+            ),
+            None => (self.earliest, self.latest),
+        }
+    }
+
+    ///Lists everything wrong with these constraints
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = Vec::new();
+        if !(self.walk_speed > 0.0 && self.walk_speed.is_finite()) {
+            problems.push(format!(
+                "walk_speed must be a positive number of meters per second, got {}",
+                self.walk_speed
+            ));
+        }
+
+        let mut windows: Vec<(String, TimeOfDay, TimeOfDay)> =
+            vec![(String::from("every day"), self.earliest, self.latest)];
+        for (day, window) in &self.days {
+            if Weekday::from_short_name(day).is_none() {
+                problems.push(format!("'{}' is not a day (use M, Tu, W, Th, F, Sa or Su)", day));
+                continue;
+            }
+            let (earliest, latest) = (
+                window.earliest.unwrap_or(self.earliest),
+                window.latest.unwrap_or(self.latest),
+            );
+            windows.push((day.clone(), earliest, latest));
+        }
+
+        for blockout in &self.blockouts {
+            let name: String = blockout.spec();
+            if !Weekday::parse_days(&blockout.days).is_ok_and(|days| !days.is_empty()) {
+                problems.push(format!("blockout {} has days that aren't M, Tu, W, Th, F, Sa or Su", name));
+            }
+            if blockout.start >= blockout.end {
+                problems.push(format!("blockout {} must start before it ends", name));
+            }
+        }
+
+        for constraint in &self.soft {
+            let (name, weight) = match constraint {
+                SoftConstraint::Window {
+                    days,
+                    start,
+                    end,
+                    weight,
+                    ..
+                } => {
+                    let name: String =
+                        format!("soft window {}:{}-{}", days, start.hhmm(), end.hhmm());
+                    if !Weekday::parse_days(days).is_ok_and(|days| !days.is_empty()) {
+                        problems.push(format!("{} has days that aren't M, Tu, W, Th, F, Sa or Su", name));
+                    }
+                    if start >= end {
+                        problems.push(format!("{} must go from one time to a later one", name));
+                    }
+                    (name, weight)
+                }
+                SoftConstraint::DayOff { day, weight } => {
+                    let name: String = format!("day off {}", day);
+                    if Weekday::from_short_name(day).is_none() {
+                        problems.push(format!("'{}' is not a day (use M, Tu, W, Th, F, Sa or Su)", day));
+                    }
+                    (name, weight)
+                }
+                SoftConstraint::AvoidBuilding { building, weight } => {
+                    (format!("avoiding {}", building), weight)
+                }
+            };
+            if !(*weight >= 0.0 && weight.is_finite()) {
+                problems.push(format!("{} needs a weight of 0 or more, got {}", name, weight));
+            }
+        }
+
+        for (day, earliest, latest) in windows {
+            if earliest >= latest {
+                problems.push(format!(
+                    "earliest ({}) must be before latest ({}) on {}",
+                    earliest, latest, day
+                ));
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blockout(days: &str, start: TimeOfDay, end: TimeOfDay) -> Blockout {
+        Blockout {
+            days: days.to_string(),
+            start,
+            end,
+            label: None,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn blockouts_parse_with_every_time_form() {
+        let lunch: Blockout = Blockout {
+            label: Some(String::from("Lunch")),
+            location: Some(String::from("STAMP")),
+            ..blockout("MWF", TimeOfDay::at(12, 0), TimeOfDay::at(13, 0))
+        };
+        for spec in [
+            "MWF:1200-1300:Lunch@STAMP",
+            "MWF:12:00-13:00:Lunch@STAMP",
+            "MWF:12:00pm-1:00pm:Lunch@STAMP",
+        ] {
+            assert_eq!(Blockout::parse(spec), Ok(lunch.clone()), "{}", spec);
+        }
+        let work: Blockout = blockout("TuTh", TimeOfDay::at(9, 30), TimeOfDay::at(11, 0));
+        assert_eq!(Blockout::parse("TuTh:9:30-11:00"), Ok(work.clone()));
+        assert_eq!(Blockout::parse("TuTh:930-1100"), Ok(work));
+        assert_eq!(Blockout::parse(&lunch.spec()), Ok(lunch));
+    }
+
+    #[test]
+    fn labels_can_hold_colons() {
+        let blockout: Blockout = Blockout::parse("M:17:00-18:00:Practice: field 2").unwrap();
+        assert_eq!(blockout.end, TimeOfDay::at(18, 0));
+        assert_eq!(blockout.label.as_deref(), Some("Practice: field 2"));
+    }
+
+    #[test]
+    fn bad_blockouts_are_errors() {
+        for spec in [
+            "MWF",
+            "MWF:1200",
+            "MWF:12:00-",
+            "MWF:12:00-lunch",
+            "MWF:noon-1300",
+        ] {
+            assert!(Blockout::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn soft_windows_parse_with_every_time_form() {
+        let lunch: SoftConstraint = SoftConstraint::Window {
+            days: String::from("MTuWThF"),
+            start: TimeOfDay::at(11, 30),
+            end: TimeOfDay::at(13, 0),
+            weight: 2.0,
+            label: Some(String::from("lunch")),
+        };
+        for spec in [
+            "MTuWThF:1130-1300:2:lunch",
+            "MTuWThF:11:30-13:00:2:lunch",
+            "MTuWThF:11:30am-1pm:2:lunch",
+        ] {
+            assert_eq!(
+                SoftConstraint::parse_window(spec),
+                Ok(lunch.clone()),
+                "{}",
+                spec
+            );
+        }
+        assert!(matches!(
+            SoftConstraint::parse_window("F:13:00-17:00:1.5"),
+            Ok(SoftConstraint::Window {
+                weight: 1.5,
+                label: None,
+                ..
+            })
+        ));
+        for spec in ["F:13:00-17:00", "F:13:00-17:00:heavy", "F:1300"] {
+            assert!(SoftConstraint::parse_window(spec).is_err(), "{}", spec);
+        }
+    }
+}
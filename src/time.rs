@@ -0,0 +1,343 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::fmt;
+use std::str::FromStr;
+
+///A time of day as minutes since midnight, from 0:00 up to and including 24:00.
+///Read from umd.io's "10:00am", "HH:MM" or the HHMM numbers older files and the command line use,
+///and saved as "HH:MM"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TimeOfDay(u16);
+
+impl TimeOfDay {
+    const END_OF_DAY: u16 = 24 * 60;
+
+    ///The time, or None when it isn't a time of day
+    pub const fn new(hour: u16, minute: u16) -> Option<TimeOfDay> {
+        //checked before any math, so a huge hour or minute can't overflow
+        if minute >= 60 || hour > 24 {
+            return None;
+        }
+        let minutes: u16 = hour * 60 + minute;
+        match minutes <= TimeOfDay::END_OF_DAY {
+            true => Some(TimeOfDay(minutes)),
+            false => None,
+        }
+    }
+
+    ///A time known to be valid, for constants
+    pub const fn at(hour: u16, minute: u16) -> TimeOfDay {
+        match TimeOfDay::new(hour, minute) {
+            Some(time) => time,
+            None => panic!("not a time of day"),
+        }
+    }
+
+    ///Reads an HHMM number like 930 or 1700
+    pub fn from_hhmm(hhmm: u32) -> Option<TimeOfDay> {
+        let hour: u16 = u16::try_from(hhmm / 100).ok()?;
+        TimeOfDay::new(hour, (hhmm % 100) as u16)
+    }
+
+    ///The time as an HHMM number, the form used on the command line
+    pub fn hhmm(self) -> u32 {
+        (self.0 / 60 * 100 + self.0 % 60) as u32
+    }
+
+    pub fn minutes(self) -> u32 {
+        self.0 as u32
+    }
+
+    ///Minutes from this time until `later`, 0 if `later` isn't after it
+    pub fn minutes_until(self, later: TimeOfDay) -> u32 {
+        later.minutes().saturating_sub(self.minutes())
+    }
+
+    ///Reads a 12 hour time like umd.io gives, e.g. "10:00am", "12:30pm" or "9am"
+    pub fn parse_12h(time: &str) -> Result<TimeOfDay, String> {
+        let bad = || format!("'{}' is not a time like 10:00am", time);
+        let lower: String = time.trim().to_ascii_lowercase();
+        let (clock, pm) = match (lower.strip_suffix("am"), lower.strip_suffix("pm")) {
+            (Some(clock), _) => (clock, false),
+            (_, Some(clock)) => (clock, true),
+            _ => return Err(bad()),
+        };
+        let (hour, minute) = clock.trim().split_once(':').unwrap_or((clock.trim(), "00"));
+        let hour: u16 = hour.parse().map_err(|_| bad())?;
+        let minute: u16 = minute.parse().map_err(|_| bad())?;
+        if !(1..=12).contains(&hour) {
+            return Err(bad());
+        }
+        //12am is midnight and 12pm is noon
+        let hour: u16 = hour % 12 + if pm { 12 } else { 0 };
+        TimeOfDay::new(hour, minute).ok_or_else(bad)
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    ///Reads "10:00am", 24 hour "HH:MM" or HHMM
+    fn from_str(time: &str) -> Result<TimeOfDay, String> {
+        let time: &str = time.trim();
+        let bad = || format!("'{}' is not a time like 930, 09:30 or 9:30am", time);
+        if time.to_ascii_lowercase().ends_with('m') {
+            return TimeOfDay::parse_12h(time);
+        }
+        match time.split_once(':') {
+            Some((hour, minute)) => {
+                let hour: u16 = hour.parse().map_err(|_| bad())?;
+                let minute: u16 = minute.parse().map_err(|_| bad())?;
+                TimeOfDay::new(hour, minute).ok_or_else(bad)
+            }
+            None => {
+                let hhmm: u32 = time.parse().map_err(|_| bad())?;
+                TimeOfDay::from_hhmm(hhmm).ok_or_else(bad)
+            }
+        }
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    ///Formats the time for people, e.g. 9:30am
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (hour, minute) = (self.0 / 60, self.0 % 60);
+        let suffix: &str = if (12..24).contains(&hour) { "pm" } else { "am" };
+        let hour: u16 = match hour % 12 {
+            0 => 12,
+            hour => hour,
+        };
+        write!(f, "{}:{:02}{}", hour, minute, suffix)
+    }
+}
+
+impl Serialize for TimeOfDay {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:02}:{:02}", self.0 / 60, self.0 % 60))
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeOfDay {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TimeOfDay, D::Error> {
+        struct TimeVisitor;
+
+        impl de::Visitor<'_> for TimeVisitor {
+            type Value = TimeOfDay;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a time like \"09:30\" or an HHMM number like 930")
+            }
+
+            //files written before times were saved as "HH:MM" hold HHMM numbers
+            fn visit_u64<E: de::Error>(self, hhmm: u64) -> Result<TimeOfDay, E> {
+                u32::try_from(hhmm)
+                    .ok()
+                    .and_then(TimeOfDay::from_hhmm)
+                    .ok_or_else(|| E::custom(format!("{} is not an HHMM time", hhmm)))
+            }
+
+            fn visit_i64<E: de::Error>(self, hhmm: i64) -> Result<TimeOfDay, E> {
+                match u64::try_from(hhmm) {
+                    Ok(hhmm) => self.visit_u64(hhmm),
+                    Err(_) => Err(E::custom(format!("{} is not an HHMM time", hhmm))),
+                }
+            }
+
+            fn visit_str<E: de::Error>(self, time: &str) -> Result<TimeOfDay, E> {
+                time.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(TimeVisitor)
+    }
+}
+
+///A day classes can meet on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    ///The letters umd.io uses for the day (M, Tu, W, Th, F, Sa, Su)
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Weekday::Monday => "M",
+            Weekday::Tuesday => "Tu",
+            Weekday::Wednesday => "W",
+            Weekday::Thursday => "Th",
+            Weekday::Friday => "F",
+            Weekday::Saturday => "Sa",
+            Weekday::Sunday => "Su",
+        }
+    }
+
+    pub fn from_short_name(name: &str) -> Option<Weekday> {
+        Weekday::ALL.into_iter().find(|day| day.short_name() == name)
+    }
+
+    ///Reads days written together the way umd.io does, e.g. MWF or TuTh
+    pub fn parse_days(days: &str) -> Result<Vec<Weekday>, String> {
+        let mut parsed: Vec<Weekday> = Vec::new();
+        let mut rest: &str = days;
+        while !rest.is_empty() {
+            //every day is one capital letter, maybe followed by a lowercase one
+            let len: usize = match rest.chars().nth(1) {
+                Some(c) if c.is_ascii_lowercase() => 2,
+                _ => 1,
+            };
+            let name: &str = rest.get(..len).unwrap_or(rest);
+            let day: Weekday = Weekday::from_short_name(name).ok_or_else(|| {
+                format!("'{}' has days that aren't M, Tu, W, Th, F, Sa or Su", days)
+            })?;
+            parsed.push(day);
+            rest = &rest[name.len()..];
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for Weekday {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.short_name())
+    }
+}
+
+impl Serialize for Weekday {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.short_name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Weekday {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Weekday, D::Error> {
+        struct DayVisitor;
+
+        impl de::Visitor<'_> for DayVisitor {
+            type Value = Weekday;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a day like \"M\" or \"Tu\"")
+            }
+
+            //files written before days were saved by name number them from 1 for Monday
+            fn visit_u64<E: de::Error>(self, day: u64) -> Result<Weekday, E> {
+                usize::try_from(day)
+                    .ok()
+                    .and_then(|day| day.checked_sub(1))
+                    .and_then(|i| Weekday::ALL.get(i).copied())
+                    .ok_or_else(|| E::custom(format!("{} is not a day number from 1 to 7", day)))
+            }
+
+            fn visit_str<E: de::Error>(self, day: &str) -> Result<Weekday, E> {
+                if let Some(day) = Weekday::from_short_name(day) {
+                    return Ok(day);
+                }
+                match day.parse::<u64>() {
+                    Ok(number) => self.visit_u64(number),
+                    Err(_) => Err(E::custom(format!(
+                        "'{}' is not a day (use M, Tu, W, Th, F, Sa or Su)",
+                        day
+                    ))),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(DayVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::Classtimes;
+
+    #[test]
+    fn twelve_hour_times() {
+        assert_eq!(TimeOfDay::parse_12h("12:00am"), Ok(TimeOfDay::at(0, 0)));
+        assert_eq!(TimeOfDay::parse_12h("12:30pm"), Ok(TimeOfDay::at(12, 30)));
+        assert_eq!(TimeOfDay::parse_12h("9am"), Ok(TimeOfDay::at(9, 0)));
+        assert_eq!(TimeOfDay::parse_12h("4:15PM"), Ok(TimeOfDay::at(16, 15)));
+        assert!(TimeOfDay::parse_12h("TBA").is_err());
+        assert!(TimeOfDay::parse_12h("13:00pm").is_err());
+        assert!(TimeOfDay::parse_12h("0:30am").is_err());
+    }
+
+    #[test]
+    fn every_written_form_parses() {
+        for time in ["930", "0930", "09:30", "9:30", "9:30am"] {
+            assert_eq!(time.parse(), Ok(TimeOfDay::at(9, 30)), "{}", time);
+        }
+        assert_eq!("24:00".parse(), Ok(TimeOfDay::at(24, 0)));
+    }
+
+    #[test]
+    fn out_of_range_times_are_errors_not_panics() {
+        let bad: [&str; 10] = [
+            "200000", "9:65535", "65535:00", "65535", "24:01", "2401", "9:60", "9:70000", "-930",
+            "",
+        ];
+        for time in bad {
+            assert!(time.parse::<TimeOfDay>().is_err(), "{}", time);
+        }
+        assert_eq!(TimeOfDay::new(u16::MAX, u16::MAX), None);
+        assert_eq!(TimeOfDay::from_hhmm(u32::MAX), None);
+    }
+
+    #[test]
+    fn times_display_for_people() {
+        assert_eq!(TimeOfDay::at(0, 5).to_string(), "12:05am");
+        assert_eq!(TimeOfDay::at(12, 30).to_string(), "12:30pm");
+        assert_eq!(TimeOfDay::at(17, 0).to_string(), "5:00pm");
+    }
+
+    #[test]
+    fn legacy_hhmm_numbers_still_load() {
+        let time: TimeOfDay = serde_json::from_str("930").unwrap();
+        assert_eq!(time, TimeOfDay::at(9, 30));
+        assert_eq!(serde_json::to_string(&time).unwrap(), "\"09:30\"");
+        assert!(serde_json::from_str::<TimeOfDay>("2500").is_err());
+        assert!(serde_json::from_str::<TimeOfDay>("-930").is_err());
+    }
+
+    #[test]
+    fn legacy_day_numbers_still_load() {
+        let classtimes: Classtimes = serde_json::from_str(
+            r#"{"1": [{"building": "ESJ", "start": 930, "end": 1045}], "Th": []}"#,
+        )
+        .unwrap();
+        let days: Vec<Weekday> = classtimes.keys().copied().collect();
+        assert_eq!(days, [Weekday::Monday, Weekday::Thursday]);
+        assert_eq!(classtimes[&Weekday::Monday][0].end, TimeOfDay::at(10, 45));
+        assert!(serde_json::from_str::<Weekday>("\"8\"").is_err());
+        assert!(serde_json::from_str::<Weekday>("\"0\"").is_err());
+    }
+
+    #[test]
+    fn days_written_together() {
+        assert_eq!(
+            Weekday::parse_days("MWF"),
+            Ok(vec![Weekday::Monday, Weekday::Wednesday, Weekday::Friday])
+        );
+        assert_eq!(
+            Weekday::parse_days("TuThSa"),
+            Ok(vec![Weekday::Tuesday, Weekday::Thursday, Weekday::Saturday])
+        );
+        assert!(Weekday::parse_days("MX").is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use crate::campus::PathWalks;
+use crate::structs::{BuildingData, BuildingMap};
+use haversine_rs::{distance, point::Point, units::Unit};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+///How long it takes to walk between two buildings
+pub trait WalkModel: Send + Sync {
+    ///Seconds to walk from one building to the other at `walk_speed` meters per second,
+    ///None when the model doesn't know one of them
+    fn seconds(&self, from: &str, to: &str, walk_speed: f32) -> Option<f32>;
+}
+
+impl fmt::Debug for dyn WalkModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WalkModel")
+    }
+}
+
+fn point(building: &BuildingData) -> Point {
+    Point::new(building.lat as f64, building.long as f64)
+}
+
+///Straight line walks between building coordinates
+pub struct Haversine {
+    buildings: BuildingMap,
+}
+
+impl Haversine {
+    pub fn new(buildings: BuildingMap) -> Haversine {
+        Haversine { buildings }
+    }
+
+    ///Meters between the buildings as the crow flies
+    pub fn meters(&self, from: &str, to: &str) -> Option<f32> {
+        let (from, to) = (self.buildings.get(from)?, self.buildings.get(to)?);
+        Some(distance(point(from), point(to), Unit::Meters) as f32)
+    }
+}
+
+impl WalkModel for Haversine {
+    fn seconds(&self, from: &str, to: &str, walk_speed: f32) -> Option<f32> {
+        Some(self.meters(from, to)? / walk_speed)
+    }
+}
+
+///Walks that can't cut across campus: straight north or south, then straight east or west.
+///This simulates real walking where we often have to follow horizontal and vertical roads and paths.
+///The east-west leg is measured halfway between the two latitudes, so a walk is as long both ways
+pub struct Manhattan {
+    buildings: BuildingMap,
+}
+
+impl Manhattan {
+    pub fn new(buildings: BuildingMap) -> Manhattan {
+        Manhattan { buildings }
+    }
+
+    ///Meters walked along the north-south leg and then the east-west one
+    pub fn meters(&self, from: &str, to: &str) -> Option<f32> {
+        let (from, to) = (self.buildings.get(from)?, self.buildings.get(to)?);
+        let north_south: f64 = distance(
+            Point::new(from.lat as f64, from.long as f64),
+            Point::new(to.lat as f64, from.long as f64),
+            Unit::Meters,
+        );
+        let mid_lat: f64 = (from.lat as f64 + to.lat as f64) / 2.0;
+        let east_west: f64 = distance(
+            Point::new(mid_lat, from.long as f64),
+            Point::new(mid_lat, to.long as f64),
+            Unit::Meters,
+        );
+        Some((north_south + east_west) as f32)
+    }
+}
+
+impl WalkModel for Manhattan {
+    fn seconds(&self, from: &str, to: &str, walk_speed: f32) -> Option<f32> {
+        Some(self.meters(from, to)? / walk_speed)
+    }
+}
+
+///Walking times measured ahead of time, in seconds, keyed by building and then by the building walked to.
+///A pair only listed one way is taken to be as long the other way. The walk speed isn't used,
+///the times already are what they are
+pub struct TimeTable {
+    times: HashMap<String, HashMap<String, f32>>,
+}
+
+impl TimeTable {
+    pub fn new(times: HashMap<String, HashMap<String, f32>>) -> TimeTable {
+        TimeTable { times }
+    }
+
+    ///Loads a json object like {"ESJ": {"PHY": 240}}
+    pub fn from_file(path: &Path) -> Result<TimeTable, Box<dyn std::error::Error>> {
+        let raw: String = fs::read_to_string(path)?;
+        Ok(TimeTable::new(serde_json::from_str(&raw)?))
+    }
+
+    fn lookup(&self, from: &str, to: &str) -> Option<f32> {
+        self.times.get(from)?.get(to).copied()
+    }
+}
+
+impl WalkModel for TimeTable {
+    fn seconds(&self, from: &str, to: &str, _walk_speed: f32) -> Option<f32> {
+        if from == to {
+            return Some(0.0);
+        }
+        self.lookup(from, to).or_else(|| self.lookup(to, from))
+    }
+}
+
+///Which walk model a run uses, as given on the command line or in a profile
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum WalkSpec {
+    Haversine,
+    #[default]
+    Manhattan,
+    Table(PathBuf),
+    Paths(PathBuf),
+}
+
+impl WalkSpec {
+    ///Parses `haversine`, `manhattan`, `table:PATH` or `paths:PATH`
+    pub fn parse(s: &str) -> Result<WalkSpec, String> {
+        match s.split_once(':') {
+            None if s == "haversine" => Ok(WalkSpec::Haversine),
+            None if s == "manhattan" => Ok(WalkSpec::Manhattan),
+            Some(("table", path)) if !path.is_empty() => Ok(WalkSpec::Table(PathBuf::from(path))),
+            Some(("paths", path)) if !path.is_empty() => Ok(WalkSpec::Paths(PathBuf::from(path))),
+            _ => Err(format!(
+                "unknown walk model {}, expected haversine, manhattan, table:PATH or paths:PATH",
+                s
+            )),
+        }
+    }
+
+    ///Opens the model, with distances worked out from the given building coordinates.
+    ///Shortest walks along a path graph are cached under `cache_dir`
+    pub fn open(
+        &self,
+        buildings: &BuildingMap,
+        cache_dir: &Path,
+    ) -> Result<Box<dyn WalkModel>, Box<dyn std::error::Error>> {
+        Ok(match self {
+            WalkSpec::Haversine => Box::new(Haversine::new(buildings.clone())),
+            WalkSpec::Manhattan => Box::new(Manhattan::new(buildings.clone())),
+            WalkSpec::Table(path) => Box::new(TimeTable::from_file(path)?),
+            WalkSpec::Paths(path) => Box::new(PathWalks::load(path, buildings, cache_dir)?),
+        })
+    }
+}
+
+impl TryFrom<String> for WalkSpec {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<WalkSpec, String> {
+        WalkSpec::parse(&s)
+    }
+}
+
+impl From<WalkSpec> for String {
+    fn from(spec: WalkSpec) -> String {
+        match spec {
+            WalkSpec::Haversine => String::from("haversine"),
+            WalkSpec::Manhattan => String::from("manhattan"),
+            WalkSpec::Table(path) => format!("table:{}", path.display()),
+            WalkSpec::Paths(path) => format!("paths:{}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buildings() -> BuildingMap {
+        let raw: String = fs::read_to_string("cache/buildings.json").unwrap();
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    fn close(actual: f32, expected: f32) -> bool {
+        (actual - expected).abs() < 1.0
+    }
+
+    #[test]
+    fn haversine_known_pairs() {
+        let model: Haversine = Haversine::new(buildings());
+        assert!(close(model.meters("ESJ", "PHY").unwrap(), 273.1));
+        assert!(close(model.meters("EGR", "ESJ").unwrap(), 418.1));
+        assert!(close(model.meters("PHY", "EGR").unwrap(), 183.4));
+        assert_eq!(model.meters("ESJ", "ESJ"), Some(0.0));
+    }
+
+    #[test]
+    fn manhattan_known_pairs() {
+        let model: Manhattan = Manhattan::new(buildings());
+        assert!(close(model.meters("ESJ", "PHY").unwrap(), 381.1));
+        assert!(close(model.meters("EGR", "ESJ").unwrap(), 582.7));
+        assert!(close(model.meters("PHY", "EGR").unwrap(), 201.6));
+    }
+
+    #[test]
+    fn manhattan_is_the_same_both_ways_and_never_shorter() {
+        let (manhattan, haversine) = (Manhattan::new(buildings()), Haversine::new(buildings()));
+        for (from, to) in [("ESJ", "PHY"), ("EGR", "ESJ"), ("PHY", "EGR")] {
+            let there: f32 = manhattan.meters(from, to).unwrap();
+            assert!((there - manhattan.meters(to, from).unwrap()).abs() < 0.01);
+            assert!(there >= haversine.meters(from, to).unwrap());
+        }
+    }
+
+    #[test]
+    fn seconds_use_the_walk_speed() {
+        let model: Haversine = Haversine::new(buildings());
+        let meters: f32 = model.meters("ESJ", "PHY").unwrap();
+        assert_eq!(model.seconds("ESJ", "PHY", 2.0), Some(meters / 2.0));
+    }
+
+    #[test]
+    fn unknown_buildings_are_none() {
+        assert_eq!(Manhattan::new(buildings()).seconds("ESJ", "NOPE", 1.42), None);
+        assert_eq!(Haversine::new(buildings()).seconds("NOPE", "ESJ", 1.42), None);
+    }
+
+    #[test]
+    fn time_table_works_both_ways() {
+        let times: HashMap<String, HashMap<String, f32>> =
+            serde_json::from_str(r#"{"ESJ": {"PHY": 240}}"#).unwrap();
+        let table: TimeTable = TimeTable::new(times);
+        assert_eq!(table.seconds("ESJ", "PHY", 1.42), Some(240.0));
+        assert_eq!(table.seconds("PHY", "ESJ", 1.42), Some(240.0));
+        assert_eq!(table.seconds("PHY", "PHY", 1.42), Some(0.0));
+        assert_eq!(table.seconds("ESJ", "EGR", 1.42), None);
+    }
+
+    #[test]
+    fn specs_read_back() {
+        for spec in ["haversine", "manhattan", "table:walks.json", "paths:campus.geojson"] {
+            assert_eq!(String::from(WalkSpec::parse(spec).unwrap()), spec);
+        }
+        assert!(WalkSpec::parse("teleport").is_err());
+    }
+}
@@ -1,27 +1,179 @@
-use crate::structs::DisplaySchedule;
-use axum::{Router, response::Html, routing::get};
+use crate::schedule::build_display_schedules;
+use crate::structs::{
+    Blockout, Constraints, DayWindow, DisplaySchedule, GenerateOptions, ScheduleInputs,
+};
+use axum::{
+    Router,
+    extract::{Query, State},
+    response::Html,
+    routing::get,
+};
 use minijinja::{Environment, context, path_loader};
-use std::sync::Arc;
+use serde::{Deserialize, Deserializer, de};
+use std::{fmt, str::FromStr, sync::Arc};
 
-pub async fn launch_webpage(all_schedules: Vec<DisplaySchedule>) {
+struct AppState {
+    env: Environment<'static>,
+    inputs: ScheduleInputs,
+    constraints: Constraints,
+    options: GenerateOptions,
+    //what the run warned about when the server started: courses and ratings that couldn't be
+    //fetched, unknown buildings, TBA meetings and walks that can't be timed
+    warnings: Vec<String>,
+}
+
+///Constraint overrides that can be given in the display page's query string
+#[derive(Debug, Deserialize, Default)]
+struct ConstraintsQuery {
+    #[serde(default, deserialize_with = "empty_as_none")]
+    walk_speed: Option<f32>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    //times are read in apply so a bad one shows up with the other problems
+    earliest: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    latest: Option<String>,
+    //comma separated DAY:EARLIEST-LATEST windows, e.g. F:1000-,M:-1500
+    days: Option<String>,
+    //semicolon separated DAYS:START-END[:LABEL][@BUILDING] blockouts, replacing the starting ones
+    blockouts: Option<String>,
+    //only show this many of the best schedules
+    #[serde(default, deserialize_with = "empty_as_none")]
+    top: Option<usize>,
+}
+
+///Treats a blank form field as if it was left out
+fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let raw: Option<String> = Option::deserialize(deserializer)?;
+    match raw.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(de::Error::custom),
+    }
+}
+
+impl ConstraintsQuery {
+    ///Lays the query over the constraints the server was started with
+    fn apply(&self, mut constraints: Constraints) -> Result<Constraints, Vec<String>> {
+        if let Some(walk_speed) = self.walk_speed {
+            constraints.walk_speed = walk_speed;
+        }
+        if let Some(earliest) = &self.earliest {
+            constraints.earliest = earliest.parse().map_err(|e| vec![e])?;
+        }
+        if let Some(latest) = &self.latest {
+            constraints.latest = latest.parse().map_err(|e| vec![e])?;
+        }
+        for spec in self.days.iter().flat_map(|d| d.split(',')) {
+            if spec.trim().is_empty() {
+                continue;
+            }
+            let (day, window) = DayWindow::parse(spec.trim()).map_err(|e| vec![e])?;
+            constraints.days.insert(day, window);
+        }
+        if let Some(blockouts) = &self.blockouts {
+            constraints.blockouts = Vec::new();
+            for spec in blockouts.split(';').filter(|b| !b.trim().is_empty()) {
+                constraints
+                    .blockouts
+                    .push(Blockout::parse(spec.trim()).map_err(|e| vec![e])?);
+            }
+        }
+
+        let problems: Vec<String> = constraints.problems();
+        if problems.is_empty() {
+            Ok(constraints)
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+async fn display(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ConstraintsQuery>,
+) -> Html<String> {
+    let (constraints, mut problems) = match query.apply(state.constraints.clone()) {
+        Ok(constraints) => (constraints, Vec::new()),
+        Err(problems) => (state.constraints.clone(), problems),
+    };
+    //blockouts from the page have to be in known buildings too
+    let (constraints, location_problems) = known_locations(constraints, &state);
+    problems.extend(location_problems);
+
+    //generating can take a while, so keep it off the async workers
+    let options: GenerateOptions = GenerateOptions {
+        top: query.top.or(state.options.top),
+        ..state.options
+    };
+    let top: Option<usize> = options.top;
+    let all_schedules: Vec<DisplaySchedule> = {
+        let state = state.clone();
+        let constraints = constraints.clone();
+        tokio::task::spawn_blocking(move || {
+            build_display_schedules(&state.inputs, &constraints, &options)
+        })
+        .await
+        .unwrap()
+    };
+
+    let blockouts: Vec<String> = constraints.blockouts.iter().map(|b| b.describe()).collect();
+    let blockout_specs: String = constraints
+        .blockouts
+        .iter()
+        .map(|b| b.spec())
+        .collect::<Vec<String>>()
+        .join(";");
+
+    let tmpl = state.env.get_template("display.html").unwrap();
+    let warnings: &[String] = &state.warnings;
+    let rendered = tmpl
+        .render(context! {
+            all_schedules, constraints, problems, warnings, blockouts, blockout_specs, top
+        })
+        .unwrap();
+    Html(rendered)
+}
+
+///Drops blockouts in buildings we have no coordinates for, reporting each one
+fn known_locations(mut constraints: Constraints, state: &AppState) -> (Constraints, Vec<String>) {
+    let mut problems: Vec<String> = Vec::new();
+    constraints.blockouts.retain(|b| match &b.location {
+        Some(location) if !state.inputs.buildings.contains_key(location) => {
+            problems.push(format!(
+                "Ignoring blockout {}: unknown building {}",
+                b.describe(),
+                location
+            ));
+            false
+        }
+        _ => true,
+    });
+    (constraints, problems)
+}
+
+pub async fn launch_webpage(
+    inputs: ScheduleInputs,
+    constraints: Constraints,
+    options: GenerateOptions,
+    warnings: Vec<String>,
+) {
     let mut env = Environment::new();
     env.set_loader(path_loader("templates"));
-    let env = Arc::new(env);
-
-    let app = Router::new().route(
-        "/display",
-        get({
-            let env = env.clone();
-            move || {
-                let env = env.clone();
-                async move {
-                    let tmpl = env.get_template("display.html").unwrap();
-                    let rendered = tmpl.render(context! { all_schedules }).unwrap();
-                    Html(rendered)
-                }
-            }
-        }),
-    );
+    let state = Arc::new(AppState {
+        env,
+        inputs,
+        constraints,
+        options,
+        warnings,
+    });
+
+    let app = Router::new()
+        .route("/display", get(display))
+        .with_state(state);
 
     let listener: tokio::net::TcpListener = tokio::net::TcpListener::bind("127.0.0.1:7878")
         .await